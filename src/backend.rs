@@ -0,0 +1,598 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use crate::{daemon::DaemonBackend, desktop::DesktopEntry};
+
+#[cfg(any(test, feature = "fake-backend"))]
+mod fake;
+
+#[cfg(test)]
+pub use fake::{hang, sandbox};
+
+static CURRENT: OnceLock<Box<dyn Backend>> = OnceLock::new();
+
+/// A running application together with its bundle identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppInfo {
+    pub name: String,
+    pub bundle_id: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Resources used by all of an app's processes together.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Usage {
+    /// Resident memory in bytes
+    pub memory: u64,
+    /// CPU time used since launch
+    pub cpu_time: Duration,
+    /// How long the oldest process has been running
+    pub uptime: Duration,
+}
+
+/// How an app is brought up when opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpenMode {
+    /// Launch the app, or bring it to the front if it's already running
+    #[default]
+    Focus,
+    /// Always launch another instance, even if one is running
+    NewInstance,
+    /// Launch without bringing the app to the front
+    Background,
+}
+
+/// What to hand an app when opening it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenOptions {
+    pub mode: OpenMode,
+    /// Documents to open, as absolute paths
+    pub files: Vec<PathBuf>,
+    pub urls: Vec<String>,
+    /// Command line arguments passed to the app itself
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<PathBuf>,
+    /// Launch profile to apply instead of the app's default one
+    pub profile: Option<String>,
+}
+
+impl OpenOptions {
+    pub fn with_mode(mode: OpenMode) -> Self {
+        Self { mode, ..Self::default() }
+    }
+}
+
+/// The platform operations qapp is built on.
+pub trait Backend: Send + Sync {
+    /// Visible (non-background) running applications.
+    fn running_apps(&self) -> Result<Vec<AppInfo>>;
+
+    /// Names of the visible running applications.
+    fn running_app_names(&self) -> Result<Vec<String>> {
+        Ok(self.running_apps()?.into_iter().map(|app| app.name).collect())
+    }
+
+    /// Names of the applications that can be opened.
+    fn installed_apps(&self) -> Result<Vec<String>>;
+
+    /// Opens or focuses an app by name or bundle path.
+    fn open(&self, target: &str, options: &OpenOptions) -> Result<()>;
+
+    /// Asks an app to quit.
+    fn quit(&self, app_name: &str) -> Result<()>;
+
+    /// Kills every process of an app immediately.
+    fn force_quit(&self, app_name: &str) -> Result<()>;
+
+    fn hide(&self, app_name: &str) -> Result<()>;
+
+    fn unhide(&self, app_name: &str) -> Result<()>;
+
+    /// Hides every visible app except `app_name`.
+    fn hide_others(&self, app_name: &str) -> Result<()>;
+
+    /// Minimizes all windows of an app.
+    fn minimize(&self, app_name: &str) -> Result<()>;
+
+    /// Every process belonging to an app, including helpers.
+    fn process_ids(&self, app: &AppInfo) -> Result<Vec<u32>>;
+
+    /// Memory and CPU used by an app's processes, read with `ps`.
+    fn usage(&self, app: &AppInfo) -> Result<Usage> {
+        let pids = self.process_ids(app)?;
+        if pids.is_empty() {
+            return Ok(Usage::default());
+        }
+
+        let pids: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+        let output = Command::new("ps")
+            .args(["-o", "rss=,time=,etime=", "-p", &pids.join(",")])
+            .output()
+            .context("Failed to execute ps")?;
+
+        // Processes that exited in the meantime are simply missing
+        let mut usage = Usage::default();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [rss, time, etime] = fields[..] else {
+                continue;
+            };
+
+            usage.memory += rss.parse::<u64>().unwrap_or_default() * 1024;
+            usage.cpu_time += parse_clock(time).unwrap_or_default();
+            usage.uptime = usage.uptime.max(parse_clock(etime).unwrap_or_default());
+        }
+        Ok(usage)
+    }
+}
+
+/// Parses the `[dd-][hh:]mm:ss[.cc]` times printed by `ps`.
+fn parse_clock(value: &str) -> Option<Duration> {
+    let (days, clock) = match value.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, value),
+    };
+
+    let mut seconds = 0.0;
+    for part in clock.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(Duration::from_secs(days * 24 * 60 * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// The backend for this process: a running `qapp daemon` when there is one,
+/// otherwise the local backend.
+pub fn current() -> &'static dyn Backend {
+    CURRENT
+        .get_or_init(|| match DaemonBackend::connect() {
+            Some(daemon) => Box::new(daemon),
+            None => local(),
+        })
+        .as_ref()
+}
+
+/// Makes this process use the local backend even if a daemon is running.
+pub fn use_local() {
+    let _ = CURRENT.set(local());
+}
+
+/// The fake backend when `QAPP_BACKEND=fake`, desktop entries on Linux and
+/// AppleScript otherwise.
+fn local() -> Box<dyn Backend> {
+    match std::env::var("QAPP_BACKEND").as_deref() {
+        #[cfg(any(test, feature = "fake-backend"))]
+        Ok("fake") => Box::new(fake::FakeBackend),
+        _ if cfg!(target_os = "linux") => Box::new(LinuxBackend),
+        _ => Box::new(MacBackend),
+    }
+}
+
+/// Refuses `QAPP_BACKEND=fake` in builds without the fake backend, rather
+/// than quietly acting on the real applications.
+pub fn check_selection() -> Result<()> {
+    if cfg!(not(any(test, feature = "fake-backend"))) && std::env::var("QAPP_BACKEND").as_deref() == Ok("fake") {
+        bail!("QAPP_BACKEND=fake needs a qapp built with `--features fake-backend`");
+    }
+    Ok(())
+}
+
+/// Quotes a value for use inside an AppleScript string literal.
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn run_osascript(script: &str) -> Result<String> {
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .context("Failed to execute osascript command")?;
+
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    String::from_utf8(output.stdout).context("Failed to parse osascript output")
+}
+
+// One tab-separated line per visible process; `application file` fails for
+// some processes, hence the try.
+const APP_DETAILS_SCRIPT: &str = r#"
+set out to ""
+tell application "System Events"
+    repeat with proc in (processes where background only is false)
+        set appPath to ""
+        try
+            set appPath to POSIX path of (application file of proc)
+        end try
+        set out to out & (name of proc) & tab & (bundle identifier of proc) & tab & appPath & linefeed
+    end repeat
+end tell
+return out
+"#;
+
+/// macOS, driven through `osascript`, `open` and `pkill`.
+pub struct MacBackend;
+
+impl Backend for MacBackend {
+    fn running_apps(&self) -> Result<Vec<AppInfo>> {
+        let output_str = run_osascript(APP_DETAILS_SCRIPT)?;
+
+        let non_empty = |field: Option<&str>| {
+            field
+                .map(|value| value.trim().trim_end_matches('/'))
+                .filter(|value| !value.is_empty() && *value != "missing value")
+                .map(str::to_string)
+        };
+
+        Ok(output_str
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut fields = line.split('\t');
+                AppInfo {
+                    name: fields.next().unwrap_or_default().to_string(),
+                    bundle_id: non_empty(fields.next()),
+                    path: non_empty(fields.next()),
+                }
+            })
+            .collect())
+    }
+
+    fn running_app_names(&self) -> Result<Vec<String>> {
+        let output_str = run_osascript(
+            "tell application \"System Events\" to get name of (processes where background only is false)",
+        )?;
+
+        // Parse the AppleScript output format
+        let apps: Vec<String> = output_str
+            .trim()
+            .trim_matches(|c| c == '{' || c == '}')
+            .split(", ")
+            .map(|s| s.trim_matches('"').to_string())
+            .collect();
+
+        Ok(apps)
+    }
+
+    fn installed_apps(&self) -> Result<Vec<String>> {
+        // Get list of installed applications
+        let output = Command::new("find")
+            .args(["/Applications", "-maxdepth", "2", "-name", "*.app"])
+            .output()
+            .context("Failed to list installed applications")?;
+
+        let output_str = String::from_utf8(output.stdout)
+            .context("Failed to parse find output")?;
+
+        Ok(output_str
+            .lines()
+            .map(|s| {
+                s.trim()
+                    .strip_prefix("/Applications/")
+                    .unwrap_or(s)
+                    .strip_suffix(".app")
+                    .unwrap_or(s)
+                    .to_string()
+            })
+            .collect())
+    }
+
+    fn open(&self, target: &str, options: &OpenOptions) -> Result<()> {
+        let mut command = Command::new("open");
+        match options.mode {
+            OpenMode::Focus => {},
+            OpenMode::NewInstance => {
+                command.arg("-n");
+            },
+            OpenMode::Background => {
+                command.arg("-g");
+            },
+        }
+
+        for (key, value) in &options.env {
+            command.arg("--env").arg(format!("{}={}", key, value));
+        }
+        // LaunchServices always starts apps in /, so `cwd` can't apply here
+
+        command.arg("-a").arg(target).args(&options.files).args(&options.urls);
+        if !options.args.is_empty() {
+            command.arg("--args").args(&options.args);
+        }
+
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+        spawn_reaped(&mut command).context(format!("Failed to open application: {}", target))
+    }
+
+    fn quit(&self, app_name: &str) -> Result<()> {
+        // Apps that are already gone make osascript fail, which is fine here
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!("tell application {} to quit", applescript_string(app_name)))
+            .output()
+            .context(format!("Failed to kill application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn force_quit(&self, app_name: &str) -> Result<()> {
+        // SIGKILL every process with this exact name, for apps that ignore quit
+        Command::new("pkill")
+            .args(["-KILL", "-x", app_name])
+            .output()
+            .context(format!("Failed to force kill application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn hide(&self, app_name: &str) -> Result<()> {
+        run_osascript(&format!(
+            "tell application \"System Events\" to set visible of process {} to false",
+            applescript_string(app_name),
+        ))
+        .context(format!("Failed to hide application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn unhide(&self, app_name: &str) -> Result<()> {
+        run_osascript(&format!(
+            "tell application \"System Events\" to set visible of process {} to true",
+            applescript_string(app_name),
+        ))
+        .context(format!("Failed to unhide application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn hide_others(&self, app_name: &str) -> Result<()> {
+        run_osascript(&format!(
+            "tell application \"System Events\" to set visible of (every process whose visible is true and name is not {}) to false",
+            applescript_string(app_name),
+        ))
+        .context(format!("Failed to hide applications other than {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn minimize(&self, app_name: &str) -> Result<()> {
+        // Needs accessibility permission for the terminal running qapp
+        run_osascript(&format!(
+            "tell application \"System Events\" to tell process {} to set value of attribute \"AXMinimized\" of every window to true",
+            applescript_string(app_name),
+        ))
+        .context(format!("Failed to minimize application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn process_ids(&self, app: &AppInfo) -> Result<Vec<u32>> {
+        // Helpers live inside the bundle, so match on its path when known
+        let args = match &app.path {
+            Some(path) => vec!["-f".to_string(), format!("{}/", regex_escape(path))],
+            None => vec!["-x".to_string(), app.name.clone()],
+        };
+
+        let output = Command::new("pgrep")
+            .args(&args)
+            .output()
+            .context("Failed to execute pgrep")?;
+
+        // pgrep exits with 1 when nothing matches
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .filter(|pid| *pid != std::process::id())
+            .collect())
+    }
+}
+
+/// Escapes a literal for the extended regular expressions `pgrep -f` takes,
+/// since bundle paths may contain characters such as `(`, `+` or `.`.
+fn regex_escape(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Linux desktops, driven through `.desktop` files and `/proc`. Window
+/// management differs between desktops, so hiding and minimizing aren't
+/// available.
+pub struct LinuxBackend;
+
+impl LinuxBackend {
+    /// The name to match processes on: the entry's executable when the app
+    /// is installed, the name itself otherwise.
+    fn process_name(app_name: &str) -> String {
+        DesktopEntry::find(app_name)
+            .ok()
+            .and_then(|entry| entry.process_name())
+            .unwrap_or_else(|| app_name.to_string())
+    }
+
+    fn unsupported(action: &str) -> Result<()> {
+        bail!("{} isn't supported on Linux", action)
+    }
+}
+
+impl Backend for LinuxBackend {
+    fn running_apps(&self) -> Result<Vec<AppInfo>> {
+        let processes: HashSet<String> = live_processes()?.into_iter().map(|(_, comm)| comm).collect();
+
+        let mut apps: Vec<AppInfo> = Vec::new();
+        for entry in DesktopEntry::all() {
+            let running = entry.process_name().is_some_and(|name| processes.contains(&name));
+            if running && !apps.iter().any(|app| app.name == entry.name) {
+                apps.push(AppInfo {
+                    name: entry.name,
+                    bundle_id: Some(entry.id),
+                    path: Some(entry.path.to_string_lossy().into_owned()),
+                });
+            }
+        }
+
+        Ok(apps)
+    }
+
+    fn installed_apps(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = DesktopEntry::all().into_iter().map(|entry| entry.name).collect();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn open(&self, target: &str, options: &OpenOptions) -> Result<()> {
+        // There's no portable way to raise or background another app's
+        // window, so every mode launches; single-instance apps forward
+        // the launch to their running instance themselves.
+        let entry = DesktopEntry::find(target)?;
+
+        for words in entry.commands(&options.files, &options.urls, &options.args)? {
+            let Some((program, args)) = words.split_first() else {
+                bail!("Empty Exec line in {}", entry.path.display());
+            };
+
+            let mut command = Command::new(program);
+            if let Some(cwd) = &options.cwd {
+                command.current_dir(cwd);
+            }
+
+            command
+                .args(args)
+                .envs(options.env.iter().map(|(key, value)| (key, value)))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            spawn_reaped(&mut command).context(format!("Failed to open application: {}", entry.name))?;
+        }
+
+        Ok(())
+    }
+
+    fn quit(&self, app_name: &str) -> Result<()> {
+        Command::new("pkill")
+            .args(["-TERM", "-x", &Self::process_name(app_name)])
+            .output()
+            .context(format!("Failed to kill application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn force_quit(&self, app_name: &str) -> Result<()> {
+        Command::new("pkill")
+            .args(["-KILL", "-x", &Self::process_name(app_name)])
+            .output()
+            .context(format!("Failed to force kill application: {}", app_name))?;
+
+        Ok(())
+    }
+
+    fn hide(&self, _app_name: &str) -> Result<()> {
+        Self::unsupported("Hiding applications")
+    }
+
+    fn unhide(&self, _app_name: &str) -> Result<()> {
+        Self::unsupported("Showing applications")
+    }
+
+    fn hide_others(&self, _app_name: &str) -> Result<()> {
+        Self::unsupported("Hiding applications")
+    }
+
+    fn minimize(&self, _app_name: &str) -> Result<()> {
+        Self::unsupported("Minimizing applications")
+    }
+
+    fn process_ids(&self, app: &AppInfo) -> Result<Vec<u32>> {
+        let name = Self::process_name(&app.name);
+
+        Ok(live_processes()?
+            .into_iter()
+            .filter(|(pid, comm)| *comm == name && *pid != std::process::id())
+            .map(|(pid, _)| pid)
+            .collect())
+    }
+}
+
+/// Pids and command names of the processes in `/proc`, leaving out zombies:
+/// an app that has exited but hasn't been reaped yet is no longer running.
+fn live_processes() -> Result<Vec<(u32, String)>> {
+    Ok(fs::read_dir("/proc")
+        .context("Failed to list processes")?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .filter_map(|pid: u32| {
+            let stat = fs::read(format!("/proc/{}/stat", pid)).ok()?;
+            let stat = String::from_utf8_lossy(&stat);
+            let (comm, state) = parse_stat(&stat)?;
+            // The kernel's 15-byte cut can split a character
+            let comm = comm.trim_end_matches(char::REPLACEMENT_CHARACTER);
+            (state != 'Z').then(|| (pid, comm.to_string()))
+        })
+        .collect())
+}
+
+/// The command name and state from a `/proc/<pid>/stat` line such as
+/// `42 (my app) S 1 ...`. The name may itself contain spaces and parentheses.
+fn parse_stat(stat: &str) -> Option<(&str, char)> {
+    let start = stat.find('(')?;
+    let end = stat.rfind(')')?;
+    let comm = stat.get(start + 1..end)?;
+    let state = stat.get(end + 1..)?.trim_start().chars().next()?;
+    Some((comm, state))
+}
+
+/// Starts a launched app without waiting for it, reaping it in the
+/// background once it exits so long-running qapp processes don't collect
+/// zombies that still look like running apps.
+fn spawn_reaped(command: &mut Command) -> Result<()> {
+    let mut child = command.spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ps_clock_times() {
+        assert_eq!(parse_clock("05:06.5"), Some(Duration::from_secs_f64(5.0 * 60.0 + 6.5)));
+        assert_eq!(parse_clock("02:03:04"), Some(Duration::from_secs(2 * 3600 + 3 * 60 + 4)));
+        assert_eq!(
+            parse_clock("1-02:03:04"),
+            Some(Duration::from_secs(24 * 3600 + 2 * 3600 + 3 * 60 + 4))
+        );
+        assert_eq!(parse_clock("x-02:03"), None);
+        assert_eq!(parse_clock("soon"), None);
+    }
+
+    #[test]
+    fn parses_proc_stat_lines() {
+        assert_eq!(parse_stat("42 (bash) S 1 42 42 0"), Some(("bash", 'S')));
+        assert_eq!(parse_stat("7 (my (odd) app) Z 1 7"), Some(("my (odd) app", 'Z')));
+        assert_eq!(parse_stat("7 bash S"), None);
+    }
+
+    #[test]
+    fn escapes_regex_metacharacters() {
+        assert_eq!(regex_escape("/Applications/Safari.app"), r"/Applications/Safari\.app");
+        assert_eq!(regex_escape("Notes (Beta)+[1]"), r"Notes \(Beta\)\+\[1\]");
+        assert_eq!(regex_escape(r"a\b|c"), r"a\\b\|c");
+    }
+}
@@ -0,0 +1,310 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use super::{AppInfo, Backend, OpenMode, OpenOptions, Usage};
+use crate::store;
+
+const FAKE_STATE_FILE: &str = "fake-backend.json";
+
+/// State of the fake backend, persisted so separate qapp invocations see
+/// each other's changes.
+#[derive(Debug, Serialize, Deserialize)]
+struct FakeState {
+    running: Vec<AppInfo>,
+    installed: Vec<String>,
+    hidden: Vec<String>,
+    minimized: Vec<String>,
+    /// Pretend resource use, by app name; edit the file to try out rules
+    #[serde(default)]
+    usage: BTreeMap<String, Usage>,
+    /// Apps that ignore a plain quit, for trying out force quits
+    #[serde(default)]
+    hung: Vec<String>,
+}
+
+impl Default for FakeState {
+    fn default() -> Self {
+        let installed: Vec<String> = [
+            "Safari", "Slack", "Mail", "Notes", "Terminal", "Visual Studio Code", "Docker", "Zoom",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+        Self {
+            running: ["Finder", "Terminal"].into_iter().map(FakeBackend::app_info).collect(),
+            installed,
+            hidden: Vec::new(),
+            minimized: Vec::new(),
+            usage: BTreeMap::new(),
+            hung: Vec::new(),
+        }
+    }
+}
+
+/// An in-process stand-in for the platform, for trying qapp without macOS
+/// and for unit tests. Only built for tests or with the `fake-backend`
+/// feature, and selected with `QAPP_BACKEND=fake`.
+pub struct FakeBackend;
+
+impl FakeBackend {
+    fn app_info(name: &str) -> AppInfo {
+        AppInfo {
+            name: name.to_string(),
+            bundle_id: Some(format!("com.example.{}", name.to_lowercase().replace(' ', "-"))),
+            path: Some(format!("/Applications/{}.app", name)),
+        }
+    }
+
+    fn update<T>(&self, change: impl FnOnce(&mut FakeState) -> Result<T>) -> Result<T> {
+        let mut state: FakeState = store::load(FAKE_STATE_FILE)?;
+        let result = change(&mut state)?;
+        store::save(FAKE_STATE_FILE, &state)?;
+        Ok(result)
+    }
+
+    fn remove(state: &mut FakeState, app_name: &str) {
+        state.running.retain(|app| app.name != app_name);
+        state.hidden.retain(|app| app != app_name);
+        state.minimized.retain(|app| app != app_name);
+    }
+
+    fn require_running(state: &FakeState, app_name: &str) -> Result<()> {
+        if !state.running.iter().any(|app| app.name == app_name) {
+            bail!("Application not running: {}", app_name);
+        }
+        Ok(())
+    }
+}
+
+impl Backend for FakeBackend {
+    fn running_apps(&self) -> Result<Vec<AppInfo>> {
+        Ok(store::load::<FakeState>(FAKE_STATE_FILE)?.running)
+    }
+
+    fn installed_apps(&self) -> Result<Vec<String>> {
+        Ok(store::load::<FakeState>(FAKE_STATE_FILE)?.installed)
+    }
+
+    fn open(&self, target: &str, options: &OpenOptions) -> Result<()> {
+        let mode = options.mode;
+
+        // Bundle paths open the app they name
+        let name = if target.ends_with(".app") {
+            Path::new(target)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| target.to_string())
+        } else {
+            target.to_string()
+        };
+
+        self.update(|state| {
+            if !state.installed.contains(&name) && !state.running.iter().any(|app| app.name == name) {
+                bail!("Failed to open application: {}", name);
+            }
+            if mode == OpenMode::NewInstance || !state.running.iter().any(|app| app.name == name) {
+                state.running.push(Self::app_info(&name));
+            }
+            // Background launches leave the app where it was
+            if mode != OpenMode::Background {
+                state.hidden.retain(|app| *app != name);
+                state.minimized.retain(|app| *app != name);
+            }
+            Ok(())
+        })
+    }
+
+    fn quit(&self, app_name: &str) -> Result<()> {
+        self.update(|state| {
+            Self::require_running(state, app_name)?;
+            if !state.hung.iter().any(|app| app == app_name) {
+                Self::remove(state, app_name);
+            }
+            Ok(())
+        })
+    }
+
+    fn force_quit(&self, app_name: &str) -> Result<()> {
+        self.update(|state| {
+            Self::require_running(state, app_name)?;
+            Self::remove(state, app_name);
+            Ok(())
+        })
+    }
+
+    fn hide(&self, app_name: &str) -> Result<()> {
+        self.update(|state| {
+            Self::require_running(state, app_name)?;
+            if !state.hidden.iter().any(|app| app == app_name) {
+                state.hidden.push(app_name.to_string());
+            }
+            Ok(())
+        })
+    }
+
+    fn unhide(&self, app_name: &str) -> Result<()> {
+        self.update(|state| {
+            Self::require_running(state, app_name)?;
+            state.hidden.retain(|app| app != app_name);
+            Ok(())
+        })
+    }
+
+    fn hide_others(&self, app_name: &str) -> Result<()> {
+        self.update(|state| {
+            Self::require_running(state, app_name)?;
+            state.hidden = state
+                .running
+                .iter()
+                .map(|app| app.name.clone())
+                .filter(|name| name != app_name)
+                .collect();
+            Ok(())
+        })
+    }
+
+    fn minimize(&self, app_name: &str) -> Result<()> {
+        self.update(|state| {
+            Self::require_running(state, app_name)?;
+            if !state.minimized.iter().any(|app| app == app_name) {
+                state.minimized.push(app_name.to_string());
+            }
+            Ok(())
+        })
+    }
+
+    fn process_ids(&self, app: &AppInfo) -> Result<Vec<u32>> {
+        let state: FakeState = store::load(FAKE_STATE_FILE)?;
+
+        // Pretend pids, stable per position in the running list
+        Ok(state
+            .running
+            .iter()
+            .position(|running| running.name == app.name)
+            .map(|index| vec![1000 + index as u32])
+            .unwrap_or_default())
+    }
+
+    fn usage(&self, app: &AppInfo) -> Result<Usage> {
+        let state: FakeState = store::load(FAKE_STATE_FILE)?;
+        let Some(index) = state.running.iter().position(|running| running.name == app.name) else {
+            return Ok(Usage::default());
+        };
+
+        // Apps later in the running list count as launched more recently
+        let uptime = Duration::from_secs(60 * (state.running.len() - index) as u64);
+        Ok(state.usage.get(&app.name).copied().unwrap_or(Usage {
+            memory: 100 * 1024 * 1024,
+            cpu_time: Duration::ZERO,
+            uptime,
+        }))
+    }
+}
+
+/// Makes an app ignore plain quits in the sandbox, as a hung app would.
+#[cfg(test)]
+pub fn hang(app_name: &str) {
+    store::update(FAKE_STATE_FILE, |state: &mut FakeState| {
+        state.hung.push(app_name.to_string());
+        Ok(())
+    })
+    .unwrap();
+}
+
+/// Points this test process at the fake backend and an emptied data
+/// directory. Tests touching either hold the returned guard, so they run
+/// one at a time against a fresh `Finder` and `Terminal` session.
+#[cfg(test)]
+pub fn sandbox() -> std::sync::MutexGuard<'static, ()> {
+    use std::sync::{Mutex, OnceLock, PoisonError};
+
+    static LOCK: Mutex<()> = Mutex::new(());
+    static DATA_DIR: OnceLock<std::path::PathBuf> = OnceLock::new();
+
+    // A failed test mustn't fail every later one too
+    let guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let dir = DATA_DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("qapp-test-{}", std::process::id()));
+        // SAFETY: tests only read the environment through std, which
+        // serializes access, and this runs once before any sandboxed test
+        unsafe { std::env::set_var("QAPP_DATA_DIR", &dir) };
+        let _ = super::CURRENT.set(Box::new(FakeBackend));
+        dir
+    });
+    let _ = std::fs::remove_dir_all(dir);
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend;
+
+    fn running() -> Vec<String> {
+        backend::current().running_app_names().unwrap()
+    }
+
+    #[test]
+    fn opens_installed_apps_once() {
+        let _sandbox = sandbox();
+        let fake = backend::current();
+
+        fake.open("Slack", &OpenOptions::default()).unwrap();
+        fake.open("/Applications/Slack.app", &OpenOptions::default()).unwrap();
+        assert_eq!(running(), ["Finder", "Terminal", "Slack"]);
+
+        fake.open("Slack", &OpenOptions::with_mode(OpenMode::NewInstance)).unwrap();
+        assert_eq!(running(), ["Finder", "Terminal", "Slack", "Slack"]);
+
+        assert!(fake.open("Photoshop", &OpenOptions::default()).is_err());
+    }
+
+    #[test]
+    fn quitting_forgets_window_state() {
+        let _sandbox = sandbox();
+        let fake = backend::current();
+
+        fake.hide_others("Terminal").unwrap();
+        let state: FakeState = store::load(FAKE_STATE_FILE).unwrap();
+        assert_eq!(state.hidden, ["Finder"]);
+
+        fake.minimize("Terminal").unwrap();
+        fake.quit("Terminal").unwrap();
+        let state: FakeState = store::load(FAKE_STATE_FILE).unwrap();
+        assert!(state.minimized.is_empty());
+        assert_eq!(running(), ["Finder"]);
+
+        assert!(fake.hide("Terminal").is_err());
+        assert!(fake.quit("Terminal").is_err());
+    }
+
+    #[test]
+    fn hung_apps_only_quit_when_forced() {
+        let _sandbox = sandbox();
+        let fake = backend::current();
+
+        hang("Terminal");
+        fake.quit("Terminal").unwrap();
+        assert_eq!(running(), ["Finder", "Terminal"]);
+
+        fake.force_quit("Terminal").unwrap();
+        assert_eq!(running(), ["Finder"]);
+    }
+
+    #[test]
+    fn background_opens_leave_apps_hidden() {
+        let _sandbox = sandbox();
+        let fake = backend::current();
+
+        fake.hide("Terminal").unwrap();
+        fake.open("Terminal", &OpenOptions::with_mode(OpenMode::Background)).unwrap();
+        let state: FakeState = store::load(FAKE_STATE_FILE).unwrap();
+        assert_eq!(state.hidden, ["Terminal"]);
+
+        fake.open("Terminal", &OpenOptions::default()).unwrap();
+        let state: FakeState = store::load(FAKE_STATE_FILE).unwrap();
+        assert!(state.hidden.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+const DAY: u64 = 24 * 60 * 60;
+
+pub const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Seconds the local time zone is ahead of UTC, as `date` reports it right
+/// now. Asked each time, so a long-running daemon follows DST changes.
+pub fn local_offset() -> i64 {
+    let output = Command::new("date").arg("+%z").output();
+    let Ok(output) = output else {
+        return 0;
+    };
+
+    // E.g. +0200 or -0530
+    let text = String::from_utf8_lossy(&output.stdout);
+    let text = text.trim();
+    let (sign, digits) = match text.split_at_checked(1) {
+        Some(("-", digits)) => (-1, digits),
+        Some(("+", digits)) => (1, digits),
+        _ => return 0,
+    };
+    let (Some(hours), Some(minutes)) = (
+        digits.get(..2).and_then(|hours| hours.parse::<i64>().ok()),
+        digits.get(2..4).and_then(|minutes| minutes.parse::<i64>().ok()),
+    ) else {
+        return 0;
+    };
+
+    sign * (hours * 60 * 60 + minutes * 60)
+}
+
+/// Parses a time of day such as `18:00` or `9:30` into minutes past midnight.
+pub fn parse_time_of_day(input: &str) -> Result<u32> {
+    let (hours, minutes) = input
+        .trim()
+        .split_once(':')
+        .context(format!("Invalid time '{}', expected HH:MM", input))?;
+
+    let hours: u32 = hours.parse().context(format!("Invalid time '{}'", input))?;
+    let minutes: u32 = minutes.parse().context(format!("Invalid time '{}'", input))?;
+    if hours > 23 || minutes > 59 {
+        bail!("Invalid time '{}'", input);
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// Formats minutes past midnight as `HH:MM`.
+pub fn format_time_of_day(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// A Unix timestamp seen in the local time zone.
+#[derive(Debug, Clone, Copy)]
+pub struct Local {
+    timestamp: u64,
+    offset: i64,
+}
+
+impl Local {
+    pub fn new(timestamp: u64, offset: i64) -> Self {
+        Self { timestamp, offset }
+    }
+
+    fn local_seconds(&self) -> u64 {
+        self.timestamp.saturating_add_signed(self.offset)
+    }
+
+    /// Day of the week, 0 being Monday.
+    pub fn weekday(&self) -> usize {
+        // 1970-01-01 was a Thursday
+        ((self.local_seconds() / DAY + 3) % 7) as usize
+    }
+
+    pub fn minutes_past_midnight(&self) -> u32 {
+        (self.local_seconds() % DAY / 60) as u32
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Another timestamp in the same time zone.
+    pub fn with_timestamp(&self, timestamp: u64) -> Self {
+        Self { timestamp, offset: self.offset }
+    }
+
+    /// The timestamp of `minutes` past midnight, `days` after this day.
+    pub fn at(&self, days: i64, minutes: u32) -> u64 {
+        let midnight = self.local_seconds() - self.local_seconds() % DAY;
+        let local = midnight as i64 + days * DAY as i64 + minutes as i64 * 60;
+        local.saturating_sub(self.offset).max(0) as u64
+    }
+
+    /// E.g. `Tue 18:00`.
+    pub fn describe(&self) -> String {
+        let day = WEEKDAYS[self.weekday()];
+        let mut day = day.to_string();
+        day[..1].make_ascii_uppercase();
+        format!("{} {}", day, format_time_of_day(self.minutes_past_midnight()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tuesday 2024-01-02 18:00 UTC
+    const TUESDAY_EVENING: u64 = 1_704_218_400;
+
+    #[test]
+    fn parses_and_formats_times_of_day() {
+        assert_eq!(parse_time_of_day("18:00").unwrap(), 18 * 60);
+        assert_eq!(parse_time_of_day(" 9:05 ").unwrap(), 9 * 60 + 5);
+        assert!(parse_time_of_day("24:00").is_err());
+        assert!(parse_time_of_day("12:60").is_err());
+        assert!(parse_time_of_day("noon").is_err());
+
+        assert_eq!(format_time_of_day(9 * 60 + 5), "09:05");
+    }
+
+    #[test]
+    fn reads_weekday_and_time_in_utc() {
+        let now = Local::new(TUESDAY_EVENING, 0);
+        assert_eq!(now.weekday(), 1);
+        assert_eq!(now.minutes_past_midnight(), 18 * 60);
+        assert_eq!(now.describe(), "Tue 18:00");
+        assert_eq!(Local::new(0, 0).describe(), "Thu 00:00");
+    }
+
+    #[test]
+    fn applies_the_offset() {
+        // Past midnight on Wednesday at UTC+07:00
+        let now = Local::new(TUESDAY_EVENING, 7 * 60 * 60);
+        assert_eq!(now.describe(), "Wed 01:00");
+
+        let now = Local::new(TUESDAY_EVENING, -(5 * 60 * 60 + 30 * 60));
+        assert_eq!(now.describe(), "Tue 12:30");
+    }
+
+    #[test]
+    fn finds_local_times_on_other_days() {
+        let now = Local::new(TUESDAY_EVENING, 2 * 60 * 60);
+        assert_eq!(now.at(0, 20 * 60), TUESDAY_EVENING);
+        assert_eq!(now.at(1, 20 * 60), TUESDAY_EVENING + DAY);
+        assert_eq!(now.at(-1, 8 * 60), TUESDAY_EVENING - DAY - 12 * 60 * 60);
+        assert_eq!(now.with_timestamp(now.at(0, 9 * 60)).describe(), "Tue 09:00");
+    }
+}
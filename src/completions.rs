@@ -0,0 +1,671 @@
+use anyhow::Result;
+use clap::{builder::ValueHint, Arg, Command, ValueEnum};
+
+use crate::{backend, config::Config, pins::Pins, schedule, snapshot};
+
+/// Shells `qapp completions` can write a script for.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Lists of names the completion scripts ask for with `qapp __complete`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Source {
+    Installed,
+    Running,
+    Pinned,
+    Workspaces,
+    Snapshots,
+    Scheduled,
+}
+
+/// Which names complete an argument, by subcommand path and argument id.
+fn source_for(path: &[String], arg: &str) -> Option<Source> {
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+    match (path.as_slice(), arg) {
+        (["open" | "wait"] | ["pin", "add"] | ["at", "open"], "name") => Some(Source::Installed),
+        (["kill" | "restart" | "hide" | "unhide" | "hide-others" | "minimize"], "name") => Some(Source::Running),
+        (["at", "kill" | "restart" | "hide" | "minimize"], "name") => Some(Source::Running),
+        (["pin", "rm"], "name") => Some(Source::Pinned),
+        (["workspace", "up" | "down"], "name") => Some(Source::Workspaces),
+        (["snapshot", "restore" | "diff" | "rm"], "name") => Some(Source::Snapshots),
+        (["schedule", "rm"], "id") => Some(Source::Scheduled),
+        _ => None,
+    }
+}
+
+/// Prints the names for a source, one per line.
+pub fn print_names(source: Source, config: &Config) -> Result<()> {
+    let mut names = match source {
+        Source::Installed => backend::current().installed_apps()?,
+        Source::Running => backend::current().running_app_names()?,
+        Source::Pinned => Pins::load()?.apps().to_vec(),
+        Source::Workspaces => config.workspaces.keys().cloned().collect(),
+        Source::Snapshots => snapshot::names()?,
+        Source::Scheduled => schedule::ids()?,
+    };
+
+    names.sort();
+    names.dedup();
+    for name in names.iter().filter(|name| !name.is_empty()) {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+enum Positional {
+    Names(Source),
+    Values(Vec<String>),
+    Files,
+    Any,
+}
+
+struct Opt {
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    takes_value: bool,
+    values: Vec<String>,
+}
+
+impl Opt {
+    fn flags(&self) -> Vec<String> {
+        self.long
+            .iter()
+            .map(|long| format!("--{}", long))
+            .chain(self.short.map(|short| format!("-{}", short)))
+            .collect()
+    }
+}
+
+struct Subcommand {
+    name: String,
+    aliases: Vec<String>,
+    about: String,
+}
+
+/// One (sub)command with everything that can follow it.
+struct Node {
+    path: Vec<String>,
+    subcommands: Vec<Subcommand>,
+    options: Vec<Opt>,
+    positionals: Vec<Positional>,
+}
+
+impl Node {
+    /// The `$cmdpath` value the shell scripts track, e.g. ` pin add`.
+    fn key(&self) -> String {
+        self.path.iter().map(|name| format!(" {}", name)).collect()
+    }
+
+    fn flags(&self) -> Vec<String> {
+        self.options
+            .iter()
+            .flat_map(Opt::flags)
+            .collect()
+    }
+}
+
+fn help(arg_or_cmd: Option<&clap::builder::StyledStr>) -> String {
+    arg_or_cmd
+        .map(|text| text.to_string().lines().next().unwrap_or_default().to_string())
+        .unwrap_or_default()
+}
+
+fn possible_values(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn collect(cmd: &Command, path: Vec<String>, nodes: &mut Vec<Node>) {
+    let visible_args = || cmd.get_arguments().filter(|arg| !arg.is_hide_set());
+
+    let options = visible_args()
+        .filter(|arg| !arg.is_positional())
+        .map(|arg| {
+            let takes_value = arg.get_num_args().is_some_and(|range| range.takes_values());
+            Opt {
+                long: arg.get_long().map(str::to_string),
+                short: arg.get_short(),
+                help: help(arg.get_help()),
+                takes_value,
+                values: if takes_value { possible_values(arg) } else { Vec::new() },
+            }
+        })
+        .collect();
+
+    let positionals = visible_args()
+        .filter(|arg| arg.is_positional())
+        .map(|arg| {
+            let values = possible_values(arg);
+            if let Some(source) = source_for(&path, arg.get_id().as_str()) {
+                Positional::Names(source)
+            } else if !values.is_empty() {
+                Positional::Values(values)
+            } else if matches!(arg.get_value_hint(), ValueHint::FilePath | ValueHint::AnyPath | ValueHint::DirPath) {
+                Positional::Files
+            } else {
+                Positional::Any
+            }
+        })
+        .collect();
+
+    let children: Vec<&Command> = cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+        .collect();
+
+    nodes.push(Node {
+        path: path.clone(),
+        subcommands: children
+            .iter()
+            .map(|sub| Subcommand {
+                name: sub.get_name().to_string(),
+                aliases: sub.get_all_aliases().map(str::to_string).collect(),
+                about: help(sub.get_about()),
+            })
+            .collect(),
+        options,
+        positionals,
+    });
+
+    for sub in children {
+        let mut sub_path = path.clone();
+        sub_path.push(sub.get_name().to_string());
+        collect(sub, sub_path, nodes);
+    }
+}
+
+/// The completion script for a shell, covering every subcommand of `cmd`.
+pub fn generate(shell: Shell, cmd: &mut Command) -> String {
+    // Building propagates global options such as --color to subcommands
+    cmd.build();
+
+    let mut nodes = Vec::new();
+    collect(cmd, Vec::new(), &mut nodes);
+
+    match shell {
+        Shell::Bash => bash(&nodes),
+        Shell::Zsh => zsh(&nodes),
+        Shell::Fish => fish(&nodes),
+    }
+}
+
+/// `case` arms shared by bash and zsh that walk the words before the cursor:
+/// subcommands extend `$cmdpath`, options taking a value skip the next word
+/// and anything else counts as a positional argument.
+fn walk_arms(nodes: &[Node], indent: &str) -> String {
+    let mut arms = String::new();
+
+    for node in nodes {
+        let key = node.key();
+        let value_flags: Vec<String> = node
+            .options
+            .iter()
+            .filter(|opt| opt.takes_value)
+            .flat_map(Opt::flags)
+            .map(|flag| format!("\"{}:{}\"", key, flag))
+            .collect();
+        if !value_flags.is_empty() {
+            arms.push_str(&format!("{}{}) skip=1 ;;\n", indent, value_flags.join("|")));
+        }
+
+        for sub in &node.subcommands {
+            let patterns: Vec<String> = std::iter::once(&sub.name)
+                .chain(&sub.aliases)
+                .map(|name| format!("\"{}:{}\"", key, name))
+                .collect();
+            arms.push_str(&format!("{}{}) cmdpath=\"{} {}\" ;;\n", indent, patterns.join("|"), key, sub.name));
+        }
+    }
+
+    arms.push_str(&format!("{}*:-*) ;;\n", indent));
+    arms.push_str(&format!("{}*) ((npos++)) ;;\n", indent));
+    arms
+}
+
+/// `case` arms completing the value of the option before the cursor.
+fn value_arms(nodes: &[Node], indent: &str, words: impl Fn(&[String]) -> String) -> String {
+    let mut arms = String::new();
+
+    for node in nodes {
+        for opt in node.options.iter().filter(|opt| !opt.values.is_empty()) {
+            let patterns: Vec<String> = opt
+                .flags()
+                .iter()
+                .map(|flag| format!("\"{}:{}\"", node.key(), flag))
+                .collect();
+            arms.push_str(&format!("{}{}) {} ;;\n", indent, patterns.join("|"), words(&opt.values)));
+        }
+    }
+
+    arms
+}
+
+/// How a shell completes each kind of word at the cursor.
+struct Actions<W, S> {
+    options: String,
+    subcommands: String,
+    words: W,
+    names: S,
+    files: &'static str,
+}
+
+/// The `if`/`elif` chain completing the word at the cursor for one node.
+fn cursor_branches<W, S>(node: &Node, indent: &str, actions: &Actions<W, S>) -> String
+where
+    W: Fn(&[String]) -> String,
+    S: Fn(Source) -> String,
+{
+    let mut branches = format!("{}if [[ $cur == -* ]]; then\n{}    {}\n", indent, indent, actions.options);
+
+    if !node.subcommands.is_empty() {
+        branches.push_str(&format!("{}elif ((npos == 0)); then\n{}    {}\n", indent, indent, actions.subcommands));
+    }
+
+    for (index, positional) in node.positionals.iter().enumerate() {
+        // The last positional soaks up any further words
+        let test = if index + 1 == node.positionals.len() { ">=" } else { "==" };
+        let action = match positional {
+            Positional::Names(source) => (actions.names)(*source),
+            Positional::Values(values) => (actions.words)(values),
+            Positional::Files => actions.files.to_string(),
+            Positional::Any => ":".to_string(),
+        };
+        branches.push_str(&format!("{}elif ((npos {} {})); then\n{}    {}\n", indent, test, index, indent, action));
+    }
+
+    branches.push_str(&format!("{}fi\n", indent));
+    branches
+}
+
+fn source_name(source: Source) -> String {
+    source.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+}
+
+fn bash(nodes: &[Node]) -> String {
+    let mut script = String::from(
+        r#"# bash completion for qapp
+
+_qapp_words() {
+    COMPREPLY=($(compgen -W "$1" -- "$cur"))
+}
+
+_qapp_names() {
+    local name quoted
+    COMPREPLY=()
+    while IFS= read -r name; do
+        printf -v quoted '%q' "$name"
+        if [[ $name == "$cur"* || $quoted == "$cur"* ]]; then
+            COMPREPLY+=("$quoted")
+        fi
+    done < <(qapp __complete "$1" 2>/dev/null)
+}
+
+_qapp_files() {
+    compopt -o default 2>/dev/null
+    COMPREPLY=()
+}
+
+_qapp() {
+    local cur=${COMP_WORDS[COMP_CWORD]} prev=${COMP_WORDS[COMP_CWORD-1]}
+    local cmdpath="" word skip=0 npos=0 i
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word=${COMP_WORDS[i]}
+        if ((skip)); then
+            skip=0
+            continue
+        fi
+        case "$cmdpath:$word" in
+"#,
+    );
+
+    script.push_str(&walk_arms(nodes, "            "));
+    script.push_str(
+        r#"        esac
+    done
+
+    if ((skip)); then
+        case "$cmdpath:$prev" in
+"#,
+    );
+    let words = |words: &[String]| format!("_qapp_words \"{}\"", words.join(" "));
+    script.push_str(&value_arms(nodes, "            ", words));
+    script.push_str(
+        r#"            *) _qapp_files ;;
+        esac
+        return
+    fi
+
+    case "$cmdpath" in
+"#,
+    );
+
+    for node in nodes {
+        let subcommands: Vec<String> = node.subcommands.iter().map(|sub| sub.name.clone()).collect();
+        let actions = Actions {
+            options: words(&node.flags()),
+            subcommands: words(&subcommands),
+            words,
+            names: |source| format!("_qapp_names {}", source_name(source)),
+            files: "_qapp_files",
+        };
+
+        script.push_str(&format!("        \"{}\")\n", node.key()));
+        script.push_str(&cursor_branches(node, "            ", &actions));
+        script.push_str("            ;;\n");
+    }
+
+    script.push_str(
+        r#"    esac
+}
+
+complete -F _qapp qapp
+"#,
+    );
+    script
+}
+
+/// Quotes text for a single-quoted zsh string.
+fn zsh_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+fn zsh(nodes: &[Node]) -> String {
+    let mut script = String::from(
+        r#"#compdef qapp
+
+_qapp_names() {
+    local -a names
+    names=("${(@f)$(qapp __complete $1 2>/dev/null)}")
+    compadd -a names
+}
+
+_qapp() {
+    local cur=${words[CURRENT]} prev=${words[CURRENT-1]}
+    local cmdpath="" word skip=0 npos=0 i
+    local -a described
+
+    for ((i = 2; i < CURRENT; i++)); do
+        word=${words[i]}
+        if ((skip)); then
+            skip=0
+            continue
+        fi
+        case "$cmdpath:$word" in
+"#,
+    );
+
+    script.push_str(&walk_arms(nodes, "            "));
+    script.push_str(
+        r#"        esac
+    done
+
+    if ((skip)); then
+        case "$cmdpath:$prev" in
+"#,
+    );
+    let words = |words: &[String]| {
+        format!("compadd -- {}", words.iter().map(|word| zsh_quote(word)).collect::<Vec<_>>().join(" "))
+    };
+    script.push_str(&value_arms(nodes, "            ", words));
+    script.push_str(
+        r#"            *) _default ;;
+        esac
+        return
+    fi
+
+    case "$cmdpath" in
+"#,
+    );
+
+    // Options and subcommands are offered with their descriptions
+    let described = |entries: Vec<(String, String)>, tag: &str| {
+        let items: Vec<String> = entries
+            .iter()
+            .map(|(name, about)| zsh_quote(&format!("{}:{}", name.replace(':', "\\:"), about)))
+            .collect();
+        format!("described=({}); _describe -t {} {} described", items.join(" "), tag, tag)
+    };
+
+    for node in nodes {
+        let options: Vec<(String, String)> = node
+            .options
+            .iter()
+            .flat_map(|opt| opt.flags().into_iter().map(|flag| (flag, opt.help.clone())))
+            .collect();
+        let subcommands: Vec<(String, String)> = node
+            .subcommands
+            .iter()
+            .map(|sub| (sub.name.clone(), sub.about.clone()))
+            .collect();
+
+        let actions = Actions {
+            options: described(options, "options"),
+            subcommands: described(subcommands, "commands"),
+            words,
+            names: |source| format!("_qapp_names {}", source_name(source)),
+            files: "_files",
+        };
+        let branches = cursor_branches(node, "            ", &actions);
+
+        script.push_str(&format!("        \"{}\")\n", node.key()));
+        script.push_str(&branches);
+        script.push_str("            ;;\n");
+    }
+
+    script.push_str(
+        r#"    esac
+}
+
+if [ "$funcstack[1]" = "_qapp" ]; then
+    _qapp "$@"
+else
+    compdef _qapp qapp
+fi
+"#,
+    );
+    script
+}
+
+/// Quotes text for a single-quoted fish string.
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish(nodes: &[Node]) -> String {
+    let mut script = String::from("# fish completion for qapp\n\ncomplete -c qapp -f\n");
+
+    for node in nodes {
+        // The words naming each level of the path, aliases included
+        let levels: Vec<Vec<String>> = (0..node.path.len())
+            .map(|depth| {
+                let parent = nodes.iter().find(|other| other.path == node.path[..depth]).expect("parents are collected first");
+                let sub = parent.subcommands.iter().find(|sub| sub.name == node.path[depth]).expect("path names a subcommand");
+                std::iter::once(sub.name.clone()).chain(sub.aliases.iter().cloned()).collect()
+            })
+            .collect();
+
+        let mut conditions: Vec<String> = levels
+            .iter()
+            .map(|names| format!("__fish_seen_subcommand_from {}", names.join(" ")))
+            .collect();
+        let children: Vec<String> = node
+            .subcommands
+            .iter()
+            .flat_map(|sub| std::iter::once(sub.name.clone()).chain(sub.aliases.iter().cloned()))
+            .collect();
+        if node.path.is_empty() {
+            conditions.push("__fish_use_subcommand".to_string());
+        } else if !children.is_empty() {
+            conditions.push(format!("not __fish_seen_subcommand_from {}", children.join(" ")));
+        }
+        let condition = fish_quote(&conditions.join("; and "));
+
+        script.push('\n');
+        for sub in &node.subcommands {
+            script.push_str(&format!(
+                "complete -c qapp -n {} -a {} -d {}\n",
+                condition,
+                fish_quote(&sub.name),
+                fish_quote(&sub.about),
+            ));
+        }
+
+        for opt in &node.options {
+            let mut line = format!("complete -c qapp -n {}", condition);
+            if let Some(long) = &opt.long {
+                line.push_str(&format!(" -l {}", long));
+            }
+            if let Some(short) = opt.short {
+                line.push_str(&format!(" -s {}", short));
+            }
+            if !opt.values.is_empty() {
+                line.push_str(&format!(" -x -a {}", fish_quote(&opt.values.join(" "))));
+            } else if opt.takes_value {
+                line.push_str(" -r");
+            }
+            line.push_str(&format!(" -d {}\n", fish_quote(&opt.help)));
+            script.push_str(&line);
+        }
+
+        for positional in &node.positionals {
+            let arguments = match positional {
+                Positional::Names(source) => format!(" -a {}", fish_quote(&format!("(qapp __complete {})", source_name(*source)))),
+                Positional::Values(values) => format!(" -a {}", fish_quote(&values.join(" "))),
+                Positional::Files => " -F".to_string(),
+                Positional::Any => continue,
+            };
+            script.push_str(&format!("complete -c qapp -n {}{}\n", condition, arguments));
+        }
+    }
+
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    use crate::Cli;
+
+    /// A command path as clap defines it, with the subcommands and flags
+    /// every script should offer after it.
+    struct Expected {
+        path: Vec<String>,
+        subcommands: Vec<String>,
+        flags: Vec<String>,
+    }
+
+    fn walk(cmd: &Command, path: Vec<String>, expected: &mut Vec<Expected>) {
+        let subcommands: Vec<&Command> =
+            cmd.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help").collect();
+        let flags = cmd
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set() && !arg.is_positional())
+            .flat_map(|arg| {
+                let long = arg.get_long().map(|long| format!("--{}", long));
+                long.into_iter().chain(arg.get_short().map(|short| format!("-{}", short)))
+            })
+            .collect();
+
+        expected.push(Expected {
+            path: path.clone(),
+            subcommands: subcommands.iter().map(|sub| sub.get_name().to_string()).collect(),
+            flags,
+        });
+        for sub in subcommands {
+            let mut sub_path = path.clone();
+            sub_path.push(sub.get_name().to_string());
+            walk(sub, sub_path, expected);
+        }
+    }
+
+    fn expected() -> Vec<Expected> {
+        let mut cmd = Cli::command();
+        cmd.build();
+        let mut expected = Vec::new();
+        walk(&cmd, Vec::new(), &mut expected);
+        expected
+    }
+
+    /// The `case` arm completing the words after `path` in a bash or zsh script.
+    fn arm<'a>(script: &'a str, path: &[String]) -> &'a str {
+        let key: String = path.iter().map(|name| format!(" {}", name)).collect();
+        let start = script.find(&format!("\n        \"{}\")\n", key)).unwrap_or_else(|| panic!("no arm for '{}'", key));
+        let arm = &script[start..];
+        &arm[..arm.find("\n            ;;").expect("arms end with ;;")]
+    }
+
+    fn script(shell: Shell) -> String {
+        generate(shell, &mut Cli::command())
+    }
+
+    #[test]
+    fn bash_offers_every_subcommand_and_flag() {
+        let script = script(Shell::Bash);
+        for expected in expected() {
+            let words: Vec<&str> = arm(&script, &expected.path).split([' ', '"']).collect();
+            for word in expected.subcommands.iter().chain(&expected.flags) {
+                assert!(words.contains(&word.as_str()), "bash misses {} after {:?}", word, expected.path);
+            }
+        }
+    }
+
+    #[test]
+    fn zsh_offers_every_subcommand_and_flag() {
+        let script = script(Shell::Zsh);
+        for expected in expected() {
+            let arm = arm(&script, &expected.path);
+            for word in expected.subcommands.iter().chain(&expected.flags) {
+                assert!(arm.contains(&format!("'{}:", word)), "zsh misses {} after {:?}", word, expected.path);
+            }
+        }
+    }
+
+    #[test]
+    fn fish_offers_every_subcommand_and_flag() {
+        let script = script(Shell::Fish);
+        for expected in expected() {
+            let lines: Vec<&str> = script
+                .lines()
+                .filter(|line| match expected.path.as_slice() {
+                    [] => line.contains("'__fish_use_subcommand'"),
+                    path => path.iter().all(|name| line.contains(&format!("__fish_seen_subcommand_from {}", name))),
+                })
+                .collect();
+
+            for sub in &expected.subcommands {
+                let offered = format!("-a '{}' ", sub);
+                assert!(lines.iter().any(|line| line.contains(&offered)), "fish misses {} after {:?}", sub, expected.path);
+            }
+            for flag in &expected.flags {
+                let offered = match flag.strip_prefix("--") {
+                    Some(long) => format!(" -l {} ", long),
+                    None => format!(" -s {} ", &flag[1..]),
+                };
+                assert!(lines.iter().any(|line| line.contains(&offered)), "fish misses {} after {:?}", flag, expected.path);
+            }
+        }
+    }
+
+    #[test]
+    fn every_name_source_is_completed() {
+        let (bash, zsh, fish) = (script(Shell::Bash), script(Shell::Zsh), script(Shell::Fish));
+        for source in Source::value_variants() {
+            let name = source_name(*source);
+            assert!(bash.contains(&format!("_qapp_names {}\n", name)), "bash never completes {}", name);
+            assert!(zsh.contains(&format!("_qapp_names {}\n", name)), "zsh never completes {}", name);
+            assert!(fish.contains(&format!("(qapp __complete {})", name)), "fish never completes {}", name);
+        }
+    }
+}
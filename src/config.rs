@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{
+    duration, hooks::HooksConfig, keymap::Action, profile::AppConfig, rules::Rule, schedule::Schedule,
+    theme::ThemeConfig, workspace::Workspace,
+};
+
+/// User configuration, read from `$QAPP_CONFIG` or `~/.config/qapp/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keys: KeysConfig,
+    pub kill: KillConfig,
+    /// Name of a built-in theme or one defined under `themes`
+    pub theme: Option<String>,
+    pub themes: HashMap<String, ThemeConfig>,
+    pub workspaces: BTreeMap<String, Workspace>,
+    /// Launch profiles and other per-app settings, keyed by app name
+    pub apps: BTreeMap<String, AppConfig>,
+    pub hooks: HooksConfig,
+    /// Evaluated by the daemon, in order
+    pub rules: Vec<Rule>,
+    /// Recurring commands run by the daemon
+    pub schedules: Vec<Schedule>,
+}
+
+/// Key binding overrides per mode. Each entry replaces the default chords for
+/// that action, e.g. `open = ["o", "enter"]`; an empty list unbinds it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub normal: HashMap<Action, Vec<String>>,
+    pub search: HashMap<Action, Vec<String>>,
+    pub confirm: HashMap<Action, Vec<String>>,
+}
+
+/// Safety settings for killing applications.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct KillConfig {
+    /// Ask before killing, in the list and on the command line; off by
+    /// default so scripted kills keep working
+    pub confirm: bool,
+    /// Apps that are never killed without an explicit override
+    pub protected: Vec<String>,
+    /// Also protect the terminal qapp runs in and its parent processes
+    pub protect_host: bool,
+    /// How long a restart waits for an app to quit before force quitting it
+    #[serde(deserialize_with = "duration::deserialize")]
+    pub escalate_after: Duration,
+}
+
+impl Default for KillConfig {
+    fn default() -> Self {
+        Self {
+            confirm: false,
+            protected: vec!["Finder".to_string()],
+            protect_host: true,
+            escalate_after: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .context(format!("Failed to read config file: {}", path.display()))?;
+
+        toml::from_str(&contents)
+            .context(format!("Failed to parse config file: {}", path.display()))
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("QAPP_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("qapp").join("config.toml"))
+}
@@ -0,0 +1,383 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{self, AppInfo, Backend, OpenOptions, Usage},
+    config::Config,
+    focus::{self, Enforced},
+    rules::{self, Engine, Verdict},
+    schedule,
+    store,
+    theme::{self, Role},
+};
+
+const SOCKET_FILE: &str = "daemon.sock";
+
+// The running list is refreshed on every tick, so reads normally never wait;
+// the TTL only matters right after an action invalidated it.
+const TICK: Duration = Duration::from_secs(2);
+const RUNNING_TTL: Duration = Duration::from_secs(5);
+const INSTALLED_TTL: Duration = Duration::from_secs(300);
+
+// Generous, since quitting an app can take a while
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One call to the daemon; each connection carries a single request line.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+enum Request {
+    RunningApps,
+    InstalledApps,
+    Open { target: String, options: OpenOptions },
+    Quit { app: String },
+    ForceQuit { app: String },
+    Hide { app: String },
+    Unhide { app: String },
+    HideOthers { app: String },
+    Minimize { app: String },
+    ProcessIds { app: AppInfo },
+    Usage { app: AppInfo },
+    CheckRules,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Response {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+/// `daemon.sock` in the data directory.
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(store::data_dir()?.join(SOCKET_FILE))
+}
+
+/// A backend that forwards every call to a running `qapp daemon`.
+pub struct DaemonBackend {
+    socket: PathBuf,
+}
+
+impl DaemonBackend {
+    /// The daemon's client, if one is listening and `QAPP_NO_DAEMON` isn't set.
+    pub fn connect() -> Option<Self> {
+        if std::env::var_os("QAPP_NO_DAEMON").is_some_and(|value| !value.is_empty()) {
+            return None;
+        }
+
+        let socket = socket_path().ok()?;
+        UnixStream::connect(&socket).ok()?;
+        Some(Self { socket })
+    }
+
+    /// The daemon's current verdicts for every rule, without acting on them.
+    pub fn check_rules(&self) -> Result<Vec<Verdict>> {
+        self.request(&Request::CheckRules)
+    }
+
+    fn request<T: DeserializeOwned>(&self, request: &Request) -> Result<T> {
+        let mut stream = UnixStream::connect(&self.socket).context("Failed to reach the qapp daemon")?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stream.write_all(line.as_bytes()).context("Failed to send request to the qapp daemon")?;
+
+        let mut reply = String::new();
+        BufReader::new(stream)
+            .read_line(&mut reply)
+            .context("No reply from the qapp daemon")?;
+
+        match serde_json::from_str(&reply).context("Invalid reply from the qapp daemon")? {
+            Response::Ok(value) => Ok(serde_json::from_value(value)?),
+            Response::Error(message) => Err(anyhow!(message)),
+        }
+    }
+}
+
+impl Backend for DaemonBackend {
+    fn running_apps(&self) -> Result<Vec<AppInfo>> {
+        self.request(&Request::RunningApps)
+    }
+
+    fn installed_apps(&self) -> Result<Vec<String>> {
+        self.request(&Request::InstalledApps)
+    }
+
+    fn open(&self, target: &str, options: &OpenOptions) -> Result<()> {
+        self.request(&Request::Open { target: target.to_string(), options: options.clone() })
+    }
+
+    fn quit(&self, app_name: &str) -> Result<()> {
+        self.request(&Request::Quit { app: app_name.to_string() })
+    }
+
+    fn force_quit(&self, app_name: &str) -> Result<()> {
+        self.request(&Request::ForceQuit { app: app_name.to_string() })
+    }
+
+    fn hide(&self, app_name: &str) -> Result<()> {
+        self.request(&Request::Hide { app: app_name.to_string() })
+    }
+
+    fn unhide(&self, app_name: &str) -> Result<()> {
+        self.request(&Request::Unhide { app: app_name.to_string() })
+    }
+
+    fn hide_others(&self, app_name: &str) -> Result<()> {
+        self.request(&Request::HideOthers { app: app_name.to_string() })
+    }
+
+    fn minimize(&self, app_name: &str) -> Result<()> {
+        self.request(&Request::Minimize { app: app_name.to_string() })
+    }
+
+    fn process_ids(&self, app: &AppInfo) -> Result<Vec<u32>> {
+        self.request(&Request::ProcessIds { app: app.clone() })
+    }
+
+    fn usage(&self, app: &AppInfo) -> Result<Usage> {
+        self.request(&Request::Usage { app: app.clone() })
+    }
+}
+
+#[derive(Default)]
+struct Cache {
+    running: Option<(Instant, Vec<AppInfo>)>,
+    installed: Option<(Instant, Vec<String>)>,
+}
+
+/// The server side: the platform backend plus warm copies of its lists.
+struct Daemon {
+    backend: &'static dyn Backend,
+    cache: Mutex<Cache>,
+    rules: Mutex<Engine>,
+    /// From the latest evaluation, for `qapp rules check`
+    verdicts: Mutex<Option<Vec<Verdict>>>,
+}
+
+impl Daemon {
+    fn cache(&self) -> std::sync::MutexGuard<'_, Cache> {
+        // A panicking request thread doesn't make the cached lists wrong
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn rules(&self) -> std::sync::MutexGuard<'_, Engine> {
+        self.rules.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Acts on every rule whose conditions hold. The engine is only locked
+    /// while evaluating, since a restart can take a while.
+    fn apply_rules(&self) -> Result<()> {
+        let theme = theme::current();
+        let verdicts = self.rules().evaluate()?;
+        *self.verdicts.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(verdicts.clone());
+
+        for verdict in verdicts.iter().filter(|verdict| verdict.actionable()) {
+            let escalate_after = {
+                let mut engine = self.rules();
+                engine.mark_acted(verdict);
+                engine.escalate_after()
+            };
+
+            println!(
+                "{} {} {} ({})",
+                theme.paint(&format!("{}:", verdict.rule), Role::Accent),
+                verdict.action.verb(),
+                theme.paint(&verdict.app, Role::Name),
+                verdict.details.join(", ")
+            );
+            if let Err(err) = rules::execute(verdict, escalate_after) {
+                eprintln!("{} {:#}", theme.paint("Rule failed:", Role::Danger), err);
+            }
+            self.cache().running = None;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the apps of a focus session closed and ends it on time.
+    fn enforce_focus(&self) -> Result<()> {
+        match focus::enforce()? {
+            Some(Enforced::Quit { quit, failed }) => focus::print_quit(&quit, &failed),
+            Some(Enforced::Ended(reopened)) => focus::print_ended(&reopened),
+            None => return Ok(()),
+        }
+
+        self.cache().running = None;
+        Ok(())
+    }
+
+    fn running_apps(&self) -> Result<Vec<AppInfo>> {
+        if let Some((at, apps)) = &self.cache().running
+            && at.elapsed() < RUNNING_TTL
+        {
+            return Ok(apps.clone());
+        }
+
+        let apps = self.backend.running_apps()?;
+        self.cache().running = Some((Instant::now(), apps.clone()));
+        Ok(apps)
+    }
+
+    fn installed_apps(&self) -> Result<Vec<String>> {
+        if let Some((at, apps)) = &self.cache().installed
+            && at.elapsed() < INSTALLED_TTL
+        {
+            return Ok(apps.clone());
+        }
+
+        let apps = self.backend.installed_apps()?;
+        self.cache().installed = Some((Instant::now(), apps.clone()));
+        Ok(apps)
+    }
+
+    /// Re-reads the running list, and the installed one once it's stale.
+    fn tick(&self) -> Result<()> {
+        self.cache().running = None;
+        self.running_apps()?;
+        self.installed_apps()?;
+        Ok(())
+    }
+
+    /// Applies an action and forgets the running list it changed.
+    fn act(&self, action: impl FnOnce(&dyn Backend) -> Result<()>) -> Result<serde_json::Value> {
+        let result = action(self.backend);
+        self.cache().running = None;
+        result.map(|()| serde_json::Value::Null)
+    }
+
+    fn handle(&self, request: Request) -> Result<serde_json::Value> {
+        match request {
+            Request::RunningApps => Ok(serde_json::to_value(self.running_apps()?)?),
+            Request::InstalledApps => Ok(serde_json::to_value(self.installed_apps()?)?),
+            Request::Open { target, options } => self.act(|backend| backend.open(&target, &options)),
+            Request::Quit { app } => self.act(|backend| backend.quit(&app)),
+            Request::ForceQuit { app } => self.act(|backend| backend.force_quit(&app)),
+            Request::Hide { app } => self.act(|backend| backend.hide(&app)),
+            Request::Unhide { app } => self.act(|backend| backend.unhide(&app)),
+            Request::HideOthers { app } => self.act(|backend| backend.hide_others(&app)),
+            Request::Minimize { app } => self.act(|backend| backend.minimize(&app)),
+            Request::ProcessIds { app } => Ok(serde_json::to_value(self.backend.process_ids(&app)?)?),
+            Request::Usage { app } => Ok(serde_json::to_value(self.backend.usage(&app)?)?),
+            Request::CheckRules => {
+                // Sampling again right after the rules ran would measure CPU
+                // use over a few milliseconds
+                let last = self.verdicts.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+                let verdicts = match last {
+                    Some(verdicts) => verdicts,
+                    None => self.rules().evaluate()?,
+                };
+                Ok(serde_json::to_value(verdicts)?)
+            },
+        }
+    }
+
+    fn serve(&self, stream: UnixStream) -> Result<()> {
+        let mut line = String::new();
+        // Clients probing whether the daemon is up hang up without a request
+        if BufReader::new(&stream).read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => match self.handle(request) {
+                Ok(value) => Response::Ok(value),
+                Err(err) => Response::Error(format!("{:#}", err)),
+            },
+            Err(err) => Response::Error(format!("Invalid request: {}", err)),
+        };
+
+        let mut reply = serde_json::to_string(&response)?;
+        reply.push('\n');
+        (&stream).write_all(reply.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Runs the daemon in the foreground until it's killed.
+pub fn run(config: &Config) -> Result<()> {
+    let theme = theme::current();
+    let path = socket_path()?;
+
+    if UnixStream::connect(&path).is_ok() {
+        bail!("A qapp daemon is already listening on {}", path.display());
+    }
+
+    // Left behind by a daemon that didn't shut down cleanly
+    let _ = fs::remove_file(&path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+
+    // The daemon serves the platform itself rather than talking to itself
+    backend::use_local();
+    let daemon = Arc::new(Daemon {
+        backend: backend::current(),
+        cache: Mutex::default(),
+        rules: Mutex::new(Engine::new(config)),
+        verdicts: Mutex::default(),
+    });
+    daemon.tick()?;
+
+    let listener = UnixListener::bind(&path).context(format!("Failed to listen on {}", path.display()))?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+        .context(format!("Failed to restrict {}", path.display()))?;
+
+    println!("{} {}", theme.paint("Listening on:", Role::Success), path.display());
+
+    // The background loops borrow the config; connections each get a thread
+    thread::scope(|scope| {
+        scope.spawn(|| loop {
+            thread::sleep(TICK);
+            if let Err(err) = daemon.tick() {
+                eprintln!("{} {:#}", theme.paint("Refresh failed:", Role::Danger), err);
+            }
+            if let Err(err) = daemon.enforce_focus() {
+                eprintln!("{} {:#}", theme.paint("Focus failed:", Role::Danger), err);
+            }
+            if let Err(err) = schedule::run_due(config) {
+                eprintln!("{} {:#}", theme.paint("Schedule failed:", Role::Danger), err);
+            }
+        });
+
+        if !daemon.rules().is_empty() {
+            scope.spawn(|| loop {
+                if let Err(err) = daemon.apply_rules() {
+                    eprintln!("{} {:#}", theme.paint("Rules failed:", Role::Danger), err);
+                }
+                thread::sleep(rules::INTERVAL);
+            });
+        }
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("{} {}", theme.paint("Connection failed:", Role::Danger), err);
+                    continue;
+                },
+            };
+
+            let daemon = Arc::clone(&daemon);
+            thread::spawn(move || {
+                if let Err(err) = daemon.serve(stream) {
+                    eprintln!("{} {:#}", theme.paint("Request failed:", Role::Danger), err);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
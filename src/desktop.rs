@@ -0,0 +1,338 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// An application described by a freedesktop `.desktop` file.
+#[derive(Debug, Clone)]
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. `org.mozilla.firefox`
+    pub id: String,
+    pub path: PathBuf,
+    pub name: String,
+    exec: String,
+    icon: Option<String>,
+}
+
+impl DesktopEntry {
+    /// Reads the `[Desktop Entry]` group of a file, skipping entries that
+    /// aren't launchable applications.
+    pub fn load(path: &Path, id: String) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        let mut fields = HashMap::new();
+        let mut in_entry = false;
+
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+            } else if in_entry && let Some((key, value)) = line.split_once('=') {
+                // Localized keys such as Name[de] are ignored
+                fields.entry(key.trim()).or_insert_with(|| value.trim().to_string());
+            }
+        }
+
+        let flag = |key| fields.get(key).is_some_and(|value| value == "true");
+        if fields.get("Type").is_some_and(|kind| kind != "Application") || flag("Hidden") || flag("NoDisplay") {
+            return None;
+        }
+
+        Some(Self {
+            id,
+            path: path.to_path_buf(),
+            name: fields.get("Name")?.clone(),
+            exec: fields.get("Exec")?.clone(),
+            icon: fields.get("Icon").cloned(),
+        })
+    }
+
+    /// Every installed application, earlier data directories taking
+    /// precedence for the same desktop file ID.
+    pub fn all() -> Vec<Self> {
+        let mut seen = HashMap::new();
+        for dir in application_dirs() {
+            collect(&dir, &dir, &mut seen);
+        }
+
+        let mut entries: Vec<Self> = seen.into_values().collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries
+    }
+
+    /// Finds an application by name, desktop file ID or `.desktop` path.
+    pub fn find(target: &str) -> Result<Self> {
+        let path = Path::new(target);
+        if target.ends_with(".desktop") && path.exists() {
+            let id = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            return Self::load(path, id).context(format!("Not an application: {}", target));
+        }
+
+        Self::all()
+            .into_iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(target) || entry.id.eq_ignore_ascii_case(target))
+            .context(format!("Application not found: {}", target))
+    }
+
+    /// The executable's name as the kernel reports it, for matching processes.
+    pub fn process_name(&self) -> Option<String> {
+        let words = split_exec(&unescape_value(&self.exec)).ok()?;
+
+        // Skip `env VAR=value` wrappers
+        let program = words.iter().find(|word| *word != "env" && !word.contains('='))?;
+
+        let name = Path::new(program).file_name()?.to_string_lossy();
+        // /proc/<pid>/comm is cut to 15 bytes, possibly mid-character; the
+        // backend drops such a partial character, so stop short of it too
+        Some(name[..name.floor_char_boundary(15)].to_string())
+    }
+
+    /// The command lines that launch the app, expanding the Exec key's field
+    /// codes. Apps taking a single file or URL are launched once per item.
+    pub fn commands(&self, files: &[PathBuf], urls: &[String], args: &[String]) -> Result<Vec<Vec<String>>> {
+        let words = split_exec(&unescape_value(&self.exec))
+            .context(format!("Invalid Exec line in {}", self.path.display()))?;
+
+        let files: Vec<String> = files.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+        let mut locations = files.clone();
+        locations.extend(urls.iter().cloned());
+
+        let takes_urls = words.iter().any(|word| word == "%u" || word == "%U");
+        let takes_files = words.iter().any(|word| word == "%f" || word == "%F");
+        if !urls.is_empty() && !takes_urls {
+            bail!("{} can't open URLs", self.name);
+        }
+
+        let single = words.iter().any(|word| word == "%f" || word == "%u");
+        let items = if takes_urls { &locations } else { &files };
+        let runs: Vec<&[String]> = if single && items.len() > 1 {
+            items.chunks(1).collect()
+        } else {
+            vec![items.as_slice()]
+        };
+
+        Ok(runs
+            .into_iter()
+            .map(|run| {
+                let mut command = Vec::new();
+                for word in &words {
+                    match word.as_str() {
+                        "%f" | "%F" | "%u" | "%U" => command.extend(run.iter().cloned()),
+                        "%i" => {
+                            if let Some(icon) = &self.icon {
+                                command.extend(["--icon".to_string(), icon.clone()]);
+                            }
+                        },
+                        // Deprecated codes expand to nothing
+                        "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {},
+                        _ => command.push(self.expand_inline(word)),
+                    }
+                }
+
+                // Without a field code there's nowhere else for the files to go
+                if !takes_files && !takes_urls {
+                    command.extend(run.iter().cloned());
+                }
+                command.extend(args.iter().cloned());
+                command
+            })
+            .collect())
+    }
+
+    fn expand_inline(&self, word: &str) -> String {
+        let mut expanded = String::new();
+        let mut chars = word.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => expanded.push('%'),
+                Some('c') => expanded.push_str(&self.name),
+                Some('k') => expanded.push_str(&self.path.to_string_lossy()),
+                // Unknown or misplaced codes are dropped
+                _ => {},
+            }
+        }
+
+        expanded
+    }
+}
+
+/// `$XDG_DATA_HOME/applications` followed by those of `$XDG_DATA_DIRS`.
+fn application_dirs() -> Vec<PathBuf> {
+    let home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")),
+    };
+
+    let system = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    home.into_iter()
+        .chain(system.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+fn collect(root: &Path, dir: &Path, seen: &mut HashMap<String, DesktopEntry>) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in read_dir.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.is_dir() {
+            collect(root, &path, seen);
+            continue;
+        }
+        if path.extension().is_none_or(|ext| ext != "desktop") {
+            continue;
+        }
+
+        // Files in subdirectories get IDs like `kde-konsole`
+        let relative = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+        let id = relative.to_string_lossy().replace('/', "-");
+        if seen.contains_key(&id) {
+            continue;
+        }
+        if let Some(entry) = DesktopEntry::load(&path, id.clone()) {
+            seen.insert(id, entry);
+        }
+    }
+}
+
+/// Undoes the escapes allowed in any desktop file string value.
+fn unescape_value(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Splits an Exec value into words, honoring double-quoted arguments.
+fn split_exec(exec: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated quote in: {}", exec),
+                    }
+                }
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            },
+            c => {
+                in_word = true;
+                word.push(c);
+            },
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(exec: &str) -> DesktopEntry {
+        DesktopEntry {
+            id: "editor".to_string(),
+            path: PathBuf::from("/usr/share/applications/editor.desktop"),
+            name: "Editor".to_string(),
+            exec: exec.to_string(),
+            icon: Some("editor".to_string()),
+        }
+    }
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_exec_on_whitespace_and_quotes() {
+        assert_eq!(split_exec("editor  --new %F").unwrap(), strings(&["editor", "--new", "%F"]));
+        assert_eq!(
+            split_exec(r#""/opt/My Editor/bin" "say \"hi\"" """#).unwrap(),
+            strings(&["/opt/My Editor/bin", r#"say "hi""#, ""])
+        );
+        assert!(split_exec(r#"editor "unterminated"#).is_err());
+    }
+
+    #[test]
+    fn expands_file_lists_in_one_command() {
+        let files = [PathBuf::from("a.txt"), PathBuf::from("b.txt")];
+        let commands = entry("editor %F").commands(&files, &[], &strings(&["--wait"])).unwrap();
+        assert_eq!(commands, vec![strings(&["editor", "a.txt", "b.txt", "--wait"])]);
+    }
+
+    #[test]
+    fn launches_once_per_item_for_single_codes() {
+        let files = [PathBuf::from("a.txt")];
+        let urls = strings(&["https://example.com"]);
+        let commands = entry("editor %i %u").commands(&files, &urls, &[]).unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                strings(&["editor", "--icon", "editor", "a.txt"]),
+                strings(&["editor", "--icon", "editor", "https://example.com"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn appends_files_without_a_field_code() {
+        let files = [PathBuf::from("a.txt")];
+        let commands = entry("editor %d").commands(&files, &[], &[]).unwrap();
+        assert_eq!(commands, vec![strings(&["editor", "a.txt"])]);
+    }
+
+    #[test]
+    fn cuts_process_names_to_15_bytes() {
+        assert_eq!(entry("env LANG=C /usr/bin/gnome-system-monitor %U").process_name().unwrap(), "gnome-system-mo");
+        assert_eq!(entry("éditeur-de-texte").process_name().unwrap(), "éditeur-de-tex");
+        assert_eq!(entry("\"/opt/Émojis 😀😀😀\"").process_name().unwrap(), "Émojis 😀");
+    }
+
+    #[test]
+    fn refuses_urls_without_a_url_code() {
+        let urls = strings(&["https://example.com"]);
+        assert!(entry("editor %F").commands(&[], &urls, &[]).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// Parses durations such as `500ms`, `30s`, `45m`, `1h30m` or `2d`. A bare
+/// number is taken as seconds.
+pub fn parse(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("Empty duration");
+    }
+
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            bail!("Invalid duration '{}'", input);
+        }
+
+        let value: u64 = rest[..digits]
+            .parse()
+            .context(format!("Invalid duration '{}'", input))?;
+        rest = &rest[digits..];
+
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        rest = &rest[unit_len..];
+
+        // Seconds per unit; None for milliseconds
+        let factor = match unit {
+            "ms" => None,
+            "s" | "sec" | "secs" => Some(1),
+            "m" | "min" | "mins" => Some(60),
+            "h" | "hr" | "hrs" => Some(60 * 60),
+            "d" => Some(24 * 60 * 60),
+            _ => bail!("Unknown unit '{}' in duration '{}'", unit, input),
+        };
+        let part = match factor {
+            None => Some(Duration::from_millis(value)),
+            Some(factor) => value.checked_mul(factor).map(Duration::from_secs),
+        };
+        total = part
+            .and_then(|part| total.checked_add(part))
+            .context(format!("Duration '{}' is out of range", input))?;
+    }
+
+    Ok(total)
+}
+
+/// Serde helper for duration strings in config files.
+pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse(&value).map_err(serde::de::Error::custom)
+}
+
+/// Formats a duration the way `parse` reads it, e.g. `1h30m` or `45s`.
+pub fn format(duration: Duration) -> String {
+    let mut seconds = duration.as_secs();
+    if seconds == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+
+    let mut formatted = String::new();
+    for (unit, length) in [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60), ("s", 1)] {
+        if seconds >= length {
+            formatted.push_str(&format!("{}{}", seconds / length, unit));
+            seconds %= length;
+        }
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_and_bare_seconds() {
+        assert_eq!(parse("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse("1h30m").unwrap(), Duration::from_secs(90 * 60));
+        assert_eq!(parse(" 2d ").unwrap(), Duration::from_secs(2 * 24 * 60 * 60));
+        assert_eq!(parse("1min5secs").unwrap(), Duration::from_secs(65));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse("").is_err());
+        assert!(parse("h").is_err());
+        assert!(parse("5w").unwrap_err().to_string().contains("Unknown unit 'w'"));
+        assert!(parse("99999999999999999999d").is_err());
+        assert!(parse("9999999999999999999d").unwrap_err().to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn formats_as_parse_reads_it() {
+        assert_eq!(format(Duration::from_millis(250)), "250ms");
+        assert_eq!(format(Duration::from_secs(45)), "45s");
+        assert_eq!(format(Duration::from_secs(90 * 60)), "1h30m");
+        assert_eq!(format(Duration::from_secs(24 * 60 * 60 + 5)), "1d5s");
+
+        let duration = Duration::from_secs(3 * 60 * 60 + 7);
+        assert_eq!(parse(&format(duration)).unwrap(), duration);
+    }
+}
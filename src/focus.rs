@@ -0,0 +1,387 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{IsTerminal, Write},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    backend::OpenOptions,
+    config::Config,
+    daemon::DaemonBackend,
+    duration,
+    get_running_applications,
+    kill_specific_application, open_specific_application,
+    protect::ProtectedApps,
+    store,
+    theme::{self, Role},
+};
+
+const FOCUS_FILE: &str = "focus.json";
+
+// How often a foreground session looks for blocked apps
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A window during which some apps are kept closed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Session {
+    until: u64,
+    blocked: Vec<String>,
+    /// Reopen the apps quit at the start once the window ends
+    restore: bool,
+    /// Blocked apps quit since the session started
+    quit: Vec<String>,
+}
+
+impl Session {
+    fn blocks(&self, app_name: &str) -> bool {
+        self.blocked.iter().any(|app| app.eq_ignore_ascii_case(app_name))
+    }
+
+    fn remaining(&self) -> Duration {
+        Duration::from_secs(self.until.saturating_sub(store::now()))
+    }
+}
+
+/// Persisted so the daemon and other invocations see the session.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FocusState {
+    session: Option<Session>,
+}
+
+impl FocusState {
+    fn load() -> Result<Self> {
+        store::load(FOCUS_FILE)
+    }
+
+    fn update<R>(change: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+        store::update(FOCUS_FILE, change)
+    }
+
+    /// Remembers apps quit during the session, so they can be reopened.
+    fn record_quit(quit: &[String]) -> Result<()> {
+        Self::update(|state| {
+            if let Some(session) = &mut state.session {
+                for app in quit {
+                    if !session.quit.contains(app) {
+                        session.quit.push(app.clone());
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// What a look at the running apps did during a session.
+pub enum Enforced {
+    /// Blocked apps that had been launched and were quit again, and the
+    /// ones that couldn't be quit with the reason
+    Quit {
+        quit: Vec<String>,
+        failed: Vec<(String, String)>,
+    },
+    /// The window is over; the apps reopened, if asked to
+    Ended(Vec<String>),
+}
+
+/// Quits blocked apps that are running and blocks them until `length` has
+/// passed. Without a daemon to keep them closed, stays in the foreground
+/// until the window ends.
+pub fn start(length: Duration, blocked: &[String], restore: bool, config: &Config) -> Result<()> {
+    let theme = theme::current();
+
+    if let Some(reopened) = end_if_over()? {
+        print_ended(&reopened);
+    }
+
+    let protected = ProtectedApps::load(&config.kill);
+    if let Some(app) = blocked.iter().find(|app| protected.contains(app)) {
+        bail!("{} is protected and can't be blocked", app);
+    }
+
+    let session = Session {
+        until: store::now().saturating_add(length.as_secs()),
+        blocked: blocked.to_vec(),
+        restore,
+        quit: Vec::new(),
+    };
+    // Claimed before quitting anything, so two starts can't both win
+    FocusState::update(|state| {
+        if let Some(current) = &state.session
+            && current.remaining() > Duration::ZERO
+        {
+            bail!(
+                "A focus session is already running for another {}; stop it with `qapp focus stop`",
+                duration::format(current.remaining())
+            );
+        }
+        state.session = Some(session.clone());
+        Ok(())
+    })?;
+
+    let running: Vec<String> = get_running_applications()?
+        .into_iter()
+        .filter(|app| session.blocks(app))
+        .collect();
+    // Keep going past failures, so every app that did quit is remembered
+    // for --restore
+    let mut quit = Vec::new();
+    for app in running {
+        println!("{} {}", theme.paint("Quitting:", Role::Danger), theme.paint(&app, Role::Name));
+        match kill_specific_application(&app) {
+            Ok(()) => quit.push(app),
+            Err(err) => println!("{} {} ({:#})", theme.paint("Failed:", Role::Danger), theme.paint(&app, Role::Name), err),
+        }
+    }
+
+    FocusState::record_quit(&quit)?;
+    println!(
+        "{} {} blocked for {}",
+        theme.paint("Focusing:", Role::Success),
+        session.blocked.join(", "),
+        duration::format(length)
+    );
+
+    if DaemonBackend::connect().is_some() {
+        println!("The daemon keeps them closed; see `qapp focus status`.");
+        return Ok(());
+    }
+
+    run_in_foreground()
+}
+
+/// Enforces the session until it ends, printing the time left.
+fn run_in_foreground() -> Result<()> {
+    let interactive = std::io::stdout().is_terminal();
+    println!("Press Ctrl-C to stop watching; the session stays active until `qapp focus stop`.");
+
+    loop {
+        match enforce()? {
+            Some(Enforced::Ended(reopened)) => {
+                if interactive {
+                    println!();
+                }
+                print_ended(&reopened);
+                return Ok(());
+            },
+            Some(Enforced::Quit { quit, failed }) => {
+                if interactive {
+                    println!();
+                }
+                print_quit(&quit, &failed);
+            },
+            None => {},
+        }
+
+        if interactive && let Some(session) = FocusState::load()?.session {
+            print!("\r{} left ", duration::format(session.remaining()));
+            std::io::stdout().flush()?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Quits blocked apps that have been launched, or ends a session whose
+/// window is over. None when there's no session.
+pub fn enforce() -> Result<Option<Enforced>> {
+    let Some(session) = FocusState::load()?.session else {
+        return Ok(None);
+    };
+
+    if session.remaining().is_zero() {
+        // Whoever takes the session reopens its apps, so that only happens once
+        let ended = FocusState::update(|state| {
+            Ok(state.session.take_if(|session| session.remaining().is_zero()))
+        })?;
+        return Ok(ended.map(|session| Enforced::Ended(reopen(&session))));
+    }
+
+    let mut quit = Vec::new();
+    let mut failed = Vec::new();
+    for app in get_running_applications()?.into_iter().filter(|app| session.blocks(app)) {
+        match kill_specific_application(&app) {
+            Ok(()) => quit.push(app),
+            Err(err) => failed.push((app, format!("{:#}", err))),
+        }
+    }
+    FocusState::record_quit(&quit)?;
+
+    Ok((!quit.is_empty() || !failed.is_empty()).then_some(Enforced::Quit { quit, failed }))
+}
+
+/// Ends a session whose window is over, for when neither the daemon nor
+/// `qapp focus` was running to notice. Returns the apps it reopened, if it
+/// ended one.
+pub fn end_if_over() -> Result<Option<Vec<String>>> {
+    if let Some(session) = FocusState::load()?.session
+        && session.remaining().is_zero()
+        && let Some(Enforced::Ended(reopened)) = enforce()?
+    {
+        return Ok(Some(reopened));
+    }
+    Ok(None)
+}
+
+fn reopen(session: &Session) -> Vec<String> {
+    if !session.restore {
+        return Vec::new();
+    }
+
+    // One app failing to open shouldn't keep the others closed
+    session
+        .quit
+        .iter()
+        .filter(|app| open_specific_application(app, &OpenOptions::default()).is_ok())
+        .cloned()
+        .collect()
+}
+
+pub fn print_quit(quit: &[String], failed: &[(String, String)]) {
+    let theme = theme::current();
+    for app in quit {
+        println!("{} {}", theme.paint("Blocked:", Role::Danger), theme.paint(app, Role::Name));
+    }
+    for (app, err) in failed {
+        println!("{} {} ({})", theme.paint("Failed to block:", Role::Danger), theme.paint(app, Role::Name), err);
+    }
+}
+
+pub fn print_ended(reopened: &[String]) {
+    let theme = theme::current();
+    println!("{}", theme.paint("Focus session over.", Role::Success));
+    for app in reopened {
+        println!("{} {}", theme.paint("Reopening:", Role::Success), theme.paint(app, Role::Name));
+    }
+}
+
+/// Fails if `app_name` is blocked by the current session. A session whose
+/// window is over blocks nothing, even before it has been ended.
+pub fn check_allowed(app_name: &str) -> Result<()> {
+    if let Some(session) = FocusState::load()?.session
+        && !session.remaining().is_zero()
+        && session.blocks(app_name)
+    {
+        bail!(
+            "{} is blocked by focus mode for another {}",
+            app_name,
+            duration::format(session.remaining())
+        );
+    }
+    Ok(())
+}
+
+/// Prints the time left and the blocked apps.
+pub fn status() -> Result<()> {
+    let theme = theme::current();
+
+    if let Some(reopened) = end_if_over()? {
+        print_ended(&reopened);
+    }
+    let Some(session) = FocusState::load()?.session else {
+        println!("{}", theme.paint("No focus session.", Role::Warning));
+        return Ok(());
+    };
+
+    println!("{} {} left", theme.paint("Focusing:", Role::Success), duration::format(session.remaining()));
+    println!("{} {}", theme.paint("Blocked:", Role::Accent), session.blocked.join(", "));
+    if session.restore && !session.quit.is_empty() {
+        println!("{} {}", theme.paint("Reopening afterwards:", Role::Accent), session.quit.join(", "));
+    }
+    if DaemonBackend::connect().is_none() {
+        println!(
+            "{}",
+            theme.paint("Launches are only blocked while the daemon or `qapp focus` is running.", Role::Warning)
+        );
+    }
+    Ok(())
+}
+
+/// Ends the session early, reopening apps if it was asked to.
+pub fn stop() -> Result<()> {
+    let theme = theme::current();
+
+    let Some(session) = FocusState::update(|state| Ok(state.session.take()))? else {
+        println!("{}", theme.paint("No focus session.", Role::Warning));
+        return Ok(());
+    };
+
+    print_ended(&reopen(&session));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{self, OpenOptions};
+
+    fn begin(blocked: &[&str], restore: bool) {
+        let session = Session {
+            until: store::now() + 3600,
+            blocked: blocked.iter().map(|app| app.to_string()).collect(),
+            restore,
+            quit: Vec::new(),
+        };
+        FocusState::update(|state| {
+            state.session = Some(session);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn expire() {
+        FocusState::update(|state| {
+            state.session.as_mut().unwrap().until = store::now();
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn blocks_apps_only_while_the_window_lasts() {
+        let _sandbox = backend::sandbox();
+        begin(&["Slack"], false);
+
+        let err = check_allowed("slack").unwrap_err();
+        assert!(err.to_string().starts_with("slack is blocked by focus mode"), "{}", err);
+        assert!(check_allowed("Mail").is_ok());
+
+        expire();
+        assert!(check_allowed("Slack").is_ok());
+    }
+
+    #[test]
+    fn reopens_apps_quit_during_the_session_once() {
+        let _sandbox = backend::sandbox();
+        begin(&["Slack", "Terminal"], true);
+
+        backend::current().open("Slack", &OpenOptions::default()).unwrap();
+        let Some(Enforced::Quit { mut quit, failed }) = enforce().unwrap() else {
+            panic!("nothing quit");
+        };
+        quit.sort();
+        assert_eq!(quit, ["Slack", "Terminal"]);
+        assert!(failed.is_empty());
+        assert!(enforce().unwrap().is_none());
+
+        expire();
+        let Some(Enforced::Ended(mut reopened)) = enforce().unwrap() else {
+            panic!("session didn't end");
+        };
+        reopened.sort();
+        assert_eq!(reopened, ["Slack", "Terminal"]);
+        assert!(FocusState::load().unwrap().session.is_none());
+        assert!(end_if_over().unwrap().is_none());
+    }
+
+    #[test]
+    fn stopping_ends_the_session() {
+        let _sandbox = backend::sandbox();
+        begin(&["Slack"], false);
+
+        stop().unwrap();
+        assert!(FocusState::load().unwrap().session.is_none());
+        assert!(check_allowed("Slack").is_ok());
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::store;
+
+const FRECENCY_FILE: &str = "frecency.json";
+
+// Launch timestamps kept per app for scoring
+const MAX_VISITS: usize = 10;
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Usage {
+    count: u32,
+    last_used: u64,
+    // Most recent launches, oldest first
+    visits: Vec<u64>,
+}
+
+/// Launch counts and times per app, used to rank the search list.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Frecency {
+    apps: HashMap<String, Usage>,
+}
+
+impl Frecency {
+    pub fn load() -> Result<Self> {
+        store::load(FRECENCY_FILE)
+    }
+
+    /// Counts a launch of `app_name` now.
+    pub fn record_launch(app_name: &str) -> Result<()> {
+        let now = store::now();
+
+        store::update(FRECENCY_FILE, |frecency: &mut Self| {
+            let usage = frecency.apps.entry(app_name.to_string()).or_default();
+            usage.count += 1;
+            usage.last_used = now;
+            usage.visits.push(now);
+            let excess = usage.visits.len().saturating_sub(MAX_VISITS);
+            usage.visits.drain(..excess);
+            Ok(())
+        })
+    }
+
+    /// Recency-weighted launch count: recent launches are sampled and
+    /// weighted by age, then scaled up to the total number of launches.
+    pub fn score(&self, app_name: &str, now: u64) -> f64 {
+        let Some(usage) = self.apps.get(app_name) else {
+            return 0.0;
+        };
+
+        if usage.visits.is_empty() {
+            return 0.0;
+        }
+
+        let weights: f64 = usage
+            .visits
+            .iter()
+            .map(|visit| match now.saturating_sub(*visit) {
+                age if age <= 4 * DAY => 100.0,
+                age if age <= 14 * DAY => 70.0,
+                age if age <= 31 * DAY => 50.0,
+                age if age <= 90 * DAY => 30.0,
+                _ => 10.0,
+            })
+            .sum();
+
+        f64::from(usage.count) * weights / usage.visits.len() as f64
+    }
+
+    /// Sorts apps by descending score, keeping the existing order for ties.
+    pub fn rank(&self, apps: &mut [String]) {
+        let now = store::now();
+        apps.sort_by(|a, b| self.score(b, now).total_cmp(&self.score(a, now)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_000 * DAY;
+
+    fn frecency(count: u32, visits: &[u64]) -> Frecency {
+        let usage = Usage {
+            count,
+            last_used: visits.last().copied().unwrap_or_default(),
+            visits: visits.to_vec(),
+        };
+        Frecency { apps: HashMap::from([("Slack".to_string(), usage)]) }
+    }
+
+    #[test]
+    fn unknown_or_unvisited_apps_score_nothing() {
+        assert_eq!(frecency(3, &[NOW]).score("Mail", NOW), 0.0);
+        assert_eq!(frecency(3, &[]).score("Slack", NOW), 0.0);
+    }
+
+    #[test]
+    fn weights_visits_by_age() {
+        assert_eq!(frecency(1, &[NOW - DAY]).score("Slack", NOW), 100.0);
+        assert_eq!(frecency(1, &[NOW - 10 * DAY]).score("Slack", NOW), 70.0);
+        assert_eq!(frecency(1, &[NOW - 20 * DAY]).score("Slack", NOW), 50.0);
+        assert_eq!(frecency(1, &[NOW - 60 * DAY]).score("Slack", NOW), 30.0);
+        assert_eq!(frecency(1, &[NOW - 365 * DAY]).score("Slack", NOW), 10.0);
+    }
+
+    #[test]
+    fn scales_the_average_weight_by_launch_count() {
+        let score = frecency(20, &[NOW - 100 * DAY, NOW]).score("Slack", NOW);
+        assert_eq!(score, 20.0 * (10.0 + 100.0) / 2.0);
+    }
+
+    #[test]
+    fn ranks_by_descending_score() {
+        let mut frecency = frecency(1, &[store::now()]);
+        frecency.apps.insert(
+            "Mail".to_string(),
+            Usage { count: 5, last_used: store::now(), visits: vec![store::now()] },
+        );
+
+        let mut apps = vec!["Notes".to_string(), "Slack".to_string(), "Mail".to_string()];
+        frecency.rank(&mut apps);
+        assert_eq!(apps, ["Mail", "Slack", "Notes"]);
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::store;
+
+const HISTORY_FILE: &str = "kills.json";
+
+// Oldest kills are dropped beyond this many
+const MAX_RECORDS: usize = 50;
+
+/// Apps quit together by one kill action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillRecord {
+    pub apps: Vec<String>,
+    pub killed_at: u64,
+}
+
+/// Persisted stack of kills, most recent last, used to undo them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KillHistory {
+    records: Vec<KillRecord>,
+}
+
+impl KillHistory {
+    /// Remembers that `apps` were just killed.
+    pub fn record(apps: &[String]) -> Result<()> {
+        if apps.is_empty() {
+            return Ok(());
+        }
+
+        store::update(HISTORY_FILE, |history: &mut Self| {
+            history.records.push(KillRecord {
+                apps: apps.to_vec(),
+                killed_at: store::now(),
+            });
+
+            let excess = history.records.len().saturating_sub(MAX_RECORDS);
+            history.records.drain(..excess);
+            Ok(())
+        })
+    }
+
+    /// Puts a kill back on top, e.g. for the apps an undo couldn't relaunch.
+    pub fn restore(record: KillRecord) -> Result<()> {
+        if record.apps.is_empty() {
+            return Ok(());
+        }
+
+        store::update(HISTORY_FILE, |history: &mut Self| {
+            history.records.push(record);
+            Ok(())
+        })
+    }
+
+    /// Removes and returns the most recent kill.
+    pub fn pop() -> Result<Option<KillRecord>> {
+        store::update(HISTORY_FILE, |history: &mut Self| Ok(history.records.pop()))
+    }
+}
+
+/// Short relative description of a past timestamp, e.g. `5m ago`.
+pub fn time_ago(timestamp: u64) -> String {
+    let elapsed = store::now().saturating_sub(timestamp);
+
+    match elapsed {
+        0..60 => format!("{}s ago", elapsed),
+        60..3600 => format!("{}m ago", elapsed / 60),
+        3600..86400 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
+    }
+}
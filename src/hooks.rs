@@ -0,0 +1,381 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{backend, duration, store, watch};
+
+const LOG_FILE: &str = "hooks.log";
+
+// The log is rotated to `hooks.log.1` once it grows past this
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static HOOKS: OnceLock<HooksConfig> = OnceLock::new();
+
+// Numbers output files, since hooks can run from several threads at once
+static NEXT_OUTPUT: AtomicUsize = AtomicUsize::new(0);
+
+/// Shell commands run around opens and kills, from the `[hooks]` config table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    /// A failing command cancels the open
+    pub before_open: Vec<String>,
+    pub after_open: Vec<String>,
+    /// A failing command cancels the kill
+    pub before_kill: Vec<String>,
+    pub after_kill: Vec<String>,
+    /// Run by `qapp watch` when an app starts
+    pub on_start: Vec<String>,
+    /// Run by `qapp watch` when an app exits
+    pub on_exit: Vec<String>,
+    /// How long a command may run before it's killed
+    #[serde(deserialize_with = "duration::deserialize")]
+    pub timeout: Duration,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            before_open: Vec::new(),
+            after_open: Vec::new(),
+            before_kill: Vec::new(),
+            after_kill: Vec::new(),
+            on_start: Vec::new(),
+            on_exit: Vec::new(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    BeforeOpen,
+    AfterOpen,
+    BeforeKill,
+    AfterKill,
+    OnStart,
+    OnExit,
+}
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::BeforeOpen => "before-open",
+            HookEvent::AfterOpen => "after-open",
+            HookEvent::BeforeKill => "before-kill",
+            HookEvent::AfterKill => "after-kill",
+            HookEvent::OnStart => "on-start",
+            HookEvent::OnExit => "on-exit",
+        }
+    }
+
+    fn commands(self, hooks: &HooksConfig) -> &[String] {
+        match self {
+            HookEvent::BeforeOpen => &hooks.before_open,
+            HookEvent::AfterOpen => &hooks.after_open,
+            HookEvent::BeforeKill => &hooks.before_kill,
+            HookEvent::AfterKill => &hooks.after_kill,
+            HookEvent::OnStart => &hooks.on_start,
+            HookEvent::OnExit => &hooks.on_exit,
+        }
+    }
+
+    /// Whether a failure should stop the action the hook runs ahead of.
+    fn can_cancel(self) -> bool {
+        matches!(self, HookEvent::BeforeOpen | HookEvent::BeforeKill)
+    }
+}
+
+impl From<watch::EventKind> for HookEvent {
+    fn from(kind: watch::EventKind) -> Self {
+        match kind {
+            watch::EventKind::Started => HookEvent::OnStart,
+            watch::EventKind::Exited => HookEvent::OnExit,
+        }
+    }
+}
+
+/// The app a hook runs for, filling in the command's placeholders.
+#[derive(Debug, Clone, Default)]
+pub struct Target {
+    pub name: String,
+    pub pid: Option<u32>,
+    pub bundle_id: Option<String>,
+}
+
+impl Target {
+    /// Looks the app up among the running ones; an app that isn't running
+    /// has no pid or bundle ID.
+    fn lookup(app_name: &str) -> Self {
+        let backend = backend::current();
+        let app = backend
+            .running_apps()
+            .ok()
+            .and_then(|apps| apps.into_iter().find(|app| app.name == app_name));
+
+        match app {
+            Some(app) => Self {
+                pid: backend.process_ids(&app).ok().and_then(|pids| pids.into_iter().min()),
+                name: app.name,
+                bundle_id: app.bundle_id,
+            },
+            None => Self { name: app_name.to_string(), ..Self::default() },
+        }
+    }
+}
+
+impl From<&watch::Event> for Target {
+    fn from(event: &watch::Event) -> Self {
+        Self { name: event.name.clone(), pid: event.pid, bundle_id: event.bundle_id.clone() }
+    }
+}
+
+/// One finished hook, as a line of `hooks.log`.
+#[derive(Debug, Serialize)]
+struct LogEntry<'a> {
+    timestamp: u64,
+    event: HookEvent,
+    name: &'a str,
+    command: &'a str,
+    /// None when the command was killed or couldn't be started
+    status: Option<i32>,
+    /// The signal that killed the command, if one did
+    signal: Option<i32>,
+    timed_out: bool,
+    duration_ms: u128,
+    output: String,
+}
+
+impl LogEntry<'_> {
+    fn succeeded(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    fn describe(&self) -> String {
+        match (self.timed_out, self.status, self.signal) {
+            (true, _, _) => format!("`{}` timed out", self.command),
+            (false, Some(code), _) => format!("`{}` exited with {}", self.command, code),
+            (false, None, Some(signal)) => format!("`{}` was killed by signal {}", self.command, signal),
+            (false, None, None) => format!("`{}` failed: {}", self.command, self.output.trim()),
+        }
+    }
+}
+
+/// Makes the configured hooks available to every open and kill.
+pub fn init(config: &crate::config::Config) {
+    let _ = HOOKS.set(config.hooks.clone());
+}
+
+fn configured(event: HookEvent) -> Option<(&'static HooksConfig, &'static [String])> {
+    let hooks = HOOKS.get()?;
+    let commands = event.commands(hooks);
+    (!commands.is_empty()).then_some((hooks, commands))
+}
+
+/// Runs the hooks for `event` on an app by name. A failing `before-*` hook
+/// is returned as an error; other failures are only logged.
+pub fn run(event: HookEvent, app_name: &str) -> Result<()> {
+    // Skip the lookup when there's nothing to run
+    if configured(event).is_none() {
+        return Ok(());
+    }
+    run_for(event, &Target::lookup(app_name))
+}
+
+/// Runs `action` between the `before` and `after` hooks. The app is looked
+/// up once, beforehand, so both see its pid even after it has quit.
+pub fn around(before: HookEvent, after: HookEvent, app_name: &str, action: impl FnOnce() -> Result<()>) -> Result<()> {
+    if configured(before).is_none() && configured(after).is_none() {
+        return action();
+    }
+
+    let target = Target::lookup(app_name);
+    run_for(before, &target)?;
+    action()?;
+    run_for(after, &target)
+}
+
+/// Runs the hooks for `event` with the placeholders already known.
+pub fn run_for(event: HookEvent, target: &Target) -> Result<()> {
+    let Some((hooks, commands)) = configured(event) else {
+        return Ok(());
+    };
+
+    for command in commands {
+        let entry = execute(event, command, target, hooks.timeout);
+        // Hooks must never break qapp itself because the log can't be written
+        let _ = append_log(&entry);
+
+        if event.can_cancel() && !entry.succeeded() {
+            bail!("{} hook for {} {}", event.name(), target.name, entry.describe());
+        }
+    }
+
+    Ok(())
+}
+
+/// Quotes a value for `sh`, so app names can't inject commands.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Fills in the placeholders in one pass, so text substituted for one is
+/// never read as another.
+fn expand(command: &str, target: &Target) -> String {
+    let pid = target.pid.map(|pid| pid.to_string()).unwrap_or_default();
+    let placeholders = [
+        ("{name}", target.name.as_str()),
+        ("{pid}", pid.as_str()),
+        ("{bundle_id}", target.bundle_id.as_deref().unwrap_or_default()),
+    ];
+
+    let mut expanded = String::new();
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match placeholders.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                expanded.push_str(&shell_quote(value));
+                rest = &rest[placeholder.len()..];
+            },
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            },
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn execute<'a>(event: HookEvent, command: &'a str, target: &'a Target, timeout: Duration) -> LogEntry<'a> {
+    let started = Instant::now();
+    let mut entry = LogEntry {
+        timestamp: store::now(),
+        event,
+        name: &target.name,
+        command,
+        status: None,
+        signal: None,
+        timed_out: false,
+        duration_ms: 0,
+        output: String::new(),
+    };
+
+    match spawn_and_wait(event, command, target, timeout) {
+        Ok((status, output)) => {
+            entry.timed_out = status.is_none();
+            entry.status = status.and_then(|status| status.code());
+            entry.signal = status.and_then(|status| status.signal());
+            entry.output = output;
+        },
+        Err(err) => entry.output = format!("{:#}", err),
+    }
+
+    entry.duration_ms = started.elapsed().as_millis();
+    entry
+}
+
+/// Runs a command through `sh`, returning how it exited (None if it timed
+/// out) and its combined output.
+fn spawn_and_wait(event: HookEvent, command: &str, target: &Target, timeout: Duration) -> Result<(Option<ExitStatus>, String)> {
+    // Output goes to a file rather than a pipe, so a command that leaves a
+    // background process holding the pipe open can't hang qapp
+    let dir = store::data_dir()?;
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    let output_path = dir.join(format!(
+        "hook-output-{}-{}.tmp",
+        std::process::id(),
+        NEXT_OUTPUT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let output_file = File::create(&output_path).context(format!("Failed to create {}", output_path.display()))?;
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(expand(command, target))
+        .env("QAPP_EVENT", event.name())
+        .env("QAPP_NAME", &target.name)
+        .env("QAPP_PID", target.pid.map(|pid| pid.to_string()).unwrap_or_default())
+        .env("QAPP_BUNDLE_ID", target.bundle_id.as_deref().unwrap_or_default())
+        .stdin(Stdio::null())
+        .stdout(output_file.try_clone()?)
+        .stderr(output_file)
+        // Its own process group, so a timeout takes its children down too
+        .process_group(0)
+        .spawn()
+        .context("Failed to start sh")?;
+
+    // A timeout too long to represent never comes
+    let deadline = Instant::now().checked_add(timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let _ = Command::new("kill")
+                .args(["-KILL", "--", &format!("-{}", child.id())])
+                .stderr(Stdio::null())
+                .status();
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let output = fs::read(&output_path).unwrap_or_default();
+    let _ = fs::remove_file(&output_path);
+    Ok((status, String::from_utf8_lossy(&output).into_owned()))
+}
+
+fn append_log(entry: &LogEntry) -> Result<()> {
+    let path = store::data_dir()?.join(LOG_FILE);
+
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        fs::rename(&path, path.with_extension("log.1")).context(format!("Failed to rotate {}", path.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context(format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?).context(format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str) -> Target {
+        Target { name: name.to_string(), pid: Some(42), bundle_id: None }
+    }
+
+    #[test]
+    fn quotes_each_placeholder() {
+        let expanded = expand("echo {name} {pid} {bundle_id} {other}", &target("Slack"));
+        assert_eq!(expanded, "echo 'Slack' '42' '' {other}");
+    }
+
+    #[test]
+    fn never_expands_substituted_text() {
+        let expanded = expand("echo {name} {bundle_id}", &target("it's {bundle_id}"));
+        assert_eq!(expanded, r"echo 'it'\''s {bundle_id}' ''");
+    }
+}
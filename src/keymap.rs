@@ -0,0 +1,464 @@
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::tui::Mode;
+
+/// Named actions that keys can be bound to in the interactive list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Top,
+    Bottom,
+    PageUp,
+    PageDown,
+    Open,
+    OpenNew,
+    OpenBackground,
+    Kill,
+    ForceKill,
+    Search,
+    Refresh,
+    Cancel,
+    DeleteChar,
+    ToggleMark,
+    ClearMarks,
+    Confirm,
+    ConfirmProtected,
+    Undo,
+    TogglePin,
+    Hide,
+    Unhide,
+    HideOthers,
+    Minimize,
+    Restart,
+}
+
+impl Action {
+    fn label(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Top => "Top",
+            Action::Bottom => "Bottom",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Open => "Open",
+            Action::OpenNew => "New instance",
+            Action::OpenBackground => "Open in background",
+            Action::Kill => "Kill",
+            Action::ForceKill => "Force kill",
+            Action::Search => "Search",
+            Action::Refresh => "Refresh",
+            Action::Cancel => "Cancel",
+            Action::DeleteChar => "Delete",
+            Action::ToggleMark => "Mark",
+            Action::ClearMarks => "Unmark all",
+            Action::Confirm => "Confirm",
+            Action::ConfirmProtected => "Kill protected",
+            Action::Undo => "Undo kill",
+            Action::TogglePin => "Pin",
+            Action::Hide => "Hide",
+            Action::Unhide => "Show",
+            Action::HideOthers => "Hide others",
+            Action::Minimize => "Minimize",
+            Action::Restart => "Restart",
+        }
+    }
+}
+
+// Actions listed in the footer, in display order
+const NORMAL_HINTS: &[Action] = &[
+    Action::Up,
+    Action::Down,
+    Action::ToggleMark,
+    Action::Open,
+    Action::Kill,
+    Action::ForceKill,
+    Action::Restart,
+    Action::Undo,
+    Action::Hide,
+    Action::Search,
+    Action::Refresh,
+    Action::Quit,
+];
+
+const SEARCH_HINTS: &[Action] = &[
+    Action::Up,
+    Action::Down,
+    Action::Open,
+    Action::TogglePin,
+    Action::Cancel,
+    Action::DeleteChar,
+];
+
+const CONFIRM_HINTS: &[Action] = &[
+    Action::Confirm,
+    Action::ConfirmProtected,
+    Action::Cancel,
+];
+
+const DEFAULT_NORMAL: &[(&str, Action)] = &[
+    ("q", Action::Quit),
+    ("Q", Action::Quit),
+    ("esc", Action::Quit),
+    ("ctrl-c", Action::Quit),
+    ("up", Action::Up),
+    ("k", Action::Up),
+    ("down", Action::Down),
+    ("j", Action::Down),
+    ("g", Action::Top),
+    ("home", Action::Top),
+    ("G", Action::Bottom),
+    ("end", Action::Bottom),
+    ("pageup", Action::PageUp),
+    ("pagedown", Action::PageDown),
+    ("o", Action::Open),
+    ("O", Action::Open),
+    ("enter", Action::Open),
+    ("n", Action::OpenNew),
+    ("b", Action::OpenBackground),
+    ("K", Action::Kill),
+    ("X", Action::ForceKill),
+    ("r", Action::Restart),
+    ("u", Action::Undo),
+    ("p", Action::TogglePin),
+    ("h", Action::Hide),
+    ("s", Action::Unhide),
+    ("H", Action::HideOthers),
+    ("m", Action::Minimize),
+    ("space", Action::ToggleMark),
+    ("c", Action::ClearMarks),
+    ("/", Action::Search),
+    ("ctrl-r", Action::Refresh),
+    ("f5", Action::Refresh),
+];
+
+// Printable characters are typed into the query in search mode, so only
+// non-character keys are bound here by default.
+const DEFAULT_SEARCH: &[(&str, Action)] = &[
+    ("esc", Action::Cancel),
+    ("ctrl-c", Action::Quit),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("home", Action::Top),
+    ("end", Action::Bottom),
+    ("pageup", Action::PageUp),
+    ("pagedown", Action::PageDown),
+    ("enter", Action::Open),
+    ("alt-enter", Action::OpenNew),
+    ("ctrl-b", Action::OpenBackground),
+    ("backspace", Action::DeleteChar),
+    ("ctrl-p", Action::TogglePin),
+];
+
+const DEFAULT_CONFIRM: &[(&str, Action)] = &[
+    ("y", Action::Confirm),
+    ("enter", Action::Confirm),
+    ("Y", Action::ConfirmProtected),
+    ("n", Action::Cancel),
+    ("N", Action::Cancel),
+    ("esc", Action::Cancel),
+    ("ctrl-c", Action::Cancel),
+];
+
+/// A key plus the modifiers that must be held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Parses chords such as `q`, `G`, `ctrl-r`, `alt-enter`, `pagedown` or `f5`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = spec;
+
+        // A lone "-" is a key, not a separator
+        while let Some((prefix, rest)) = key.split_once('-') {
+            if rest.is_empty() {
+                break;
+            }
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("Unknown modifier '{}' in key '{}'", prefix, spec),
+            };
+            key = rest;
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                        _ => bail!("Unknown key '{}'", spec),
+                    },
+                }
+            }
+        };
+
+        // Characters carry their case, and `matches` ignores SHIFT for them
+        if let KeyCode::Char(c) = code
+            && modifiers.contains(KeyModifiers::SHIFT)
+        {
+            if c.is_alphabetic() {
+                bail!("Write '{}' rather than shift with a letter in key '{}'", c.to_uppercase(), spec);
+            }
+            bail!("Write the character shift types rather than shift with '{}' in key '{}'", c, spec);
+        }
+
+        Ok(Self { code, modifiers })
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // Terminals report uppercase letters with SHIFT held; the character
+        // itself already carries the case, so ignore SHIFT for characters.
+        let mut modifiers = event.modifiers;
+        if matches!(event.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self.code == event.code && self.modifiers == modifiers
+    }
+
+    fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Char(c) => c.to_string(),
+            _ => "?".to_string(),
+        };
+
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push('^');
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("M-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("S-");
+        }
+        label.push_str(&key);
+        label
+    }
+}
+
+/// Ordered chord-to-action bindings for a single mode.
+#[derive(Debug, Clone, Default)]
+struct Bindings(Vec<(KeyChord, Action)>);
+
+impl Bindings {
+    fn from_defaults(defaults: &[(&str, Action)]) -> Self {
+        Self(
+            defaults
+                .iter()
+                .map(|(spec, action)| {
+                    (KeyChord::parse(spec).expect("default key bindings are valid"), *action)
+                })
+                .collect(),
+        )
+    }
+
+    fn apply_overrides(&mut self, overrides: &HashMap<Action, Vec<String>>) -> Result<()> {
+        for (action, specs) in overrides {
+            let chords = specs
+                .iter()
+                .map(|spec| KeyChord::parse(spec))
+                .collect::<Result<Vec<_>>>()?;
+
+            // A chord can only trigger one action, so drop it from whatever it
+            // was bound to before along with this action's old chords.
+            self.0
+                .retain(|(chord, bound)| bound != action && !chords.contains(chord));
+            self.0.extend(chords.into_iter().map(|chord| (chord, *action)));
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, event: &KeyEvent) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(chord, _)| chord.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    fn hints(&self, actions: &[Action]) -> String {
+        actions
+            .iter()
+            .filter_map(|action| {
+                let keys: Vec<String> = self
+                    .0
+                    .iter()
+                    .filter(|(_, bound)| bound == action)
+                    .take(2)
+                    .map(|(chord, _)| chord.label())
+                    .collect();
+
+                if keys.is_empty() {
+                    None
+                } else {
+                    Some(format!("{}: {}", keys.join("/"), action.label()))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("   ")
+    }
+}
+
+/// Key bindings for every mode of the interactive list.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    normal: Bindings,
+    search: Bindings,
+    // Kill confirmation dialog
+    confirm: Bindings,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            normal: Bindings::from_defaults(DEFAULT_NORMAL),
+            search: Bindings::from_defaults(DEFAULT_SEARCH),
+            confirm: Bindings::from_defaults(DEFAULT_CONFIRM),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn from_config(config: &crate::config::KeysConfig) -> Result<Self> {
+        let mut keymap = Self::default();
+        keymap.normal.apply_overrides(&config.normal)?;
+        keymap.search.apply_overrides(&config.search)?;
+        keymap.confirm.apply_overrides(&config.confirm)?;
+        Ok(keymap)
+    }
+
+    fn bindings(&self, mode: &Mode) -> &Bindings {
+        match mode {
+            Mode::Normal => &self.normal,
+            Mode::Search => &self.search,
+        }
+    }
+
+    pub fn lookup(&self, mode: &Mode, event: &KeyEvent) -> Option<Action> {
+        self.bindings(mode).lookup(event)
+    }
+
+    /// Footer text describing the main bindings of the given mode.
+    pub fn hints(&self, mode: &Mode) -> String {
+        match mode {
+            Mode::Normal => self.normal.hints(NORMAL_HINTS),
+            Mode::Search => self.search.hints(SEARCH_HINTS),
+        }
+    }
+
+    pub fn lookup_confirm(&self, event: &KeyEvent) -> Option<Action> {
+        self.confirm.lookup(event)
+    }
+
+    /// Hint text for the kill confirmation dialog.
+    pub fn confirm_hints(&self, protected: bool) -> String {
+        let actions: Vec<Action> = CONFIRM_HINTS
+            .iter()
+            .copied()
+            .filter(|action| if protected {
+                *action != Action::Confirm
+            } else {
+                *action != Action::ConfirmProtected
+            })
+            .collect();
+        self.confirm.hints(&actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord { code, modifiers }
+    }
+
+    #[test]
+    fn parses_keys_and_modifiers() {
+        assert_eq!(KeyChord::parse("q").unwrap(), chord(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(KeyChord::parse("G").unwrap(), chord(KeyCode::Char('G'), KeyModifiers::NONE));
+        assert_eq!(KeyChord::parse("-").unwrap(), chord(KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!(KeyChord::parse("Ctrl-r").unwrap(), chord(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert_eq!(KeyChord::parse("ctrl--").unwrap(), chord(KeyCode::Char('-'), KeyModifiers::CONTROL));
+        assert_eq!(
+            KeyChord::parse("ctrl-alt-enter").unwrap(),
+            chord(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT)
+        );
+        assert_eq!(KeyChord::parse("shift-up").unwrap(), chord(KeyCode::Up, KeyModifiers::SHIFT));
+        assert_eq!(KeyChord::parse("PgDn").unwrap(), chord(KeyCode::PageDown, KeyModifiers::NONE));
+        assert_eq!(KeyChord::parse("f12").unwrap(), chord(KeyCode::F(12), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!(KeyChord::parse("f13").is_err());
+        assert!(KeyChord::parse("enterr").is_err());
+        assert!(KeyChord::parse("c-r").unwrap_err().to_string().contains("Unknown modifier 'c'"));
+    }
+
+    #[test]
+    fn rejects_shift_with_characters() {
+        let err = KeyChord::parse("shift-r").unwrap_err().to_string();
+        assert!(err.contains("Write 'R'"), "{}", err);
+        assert!(KeyChord::parse("shift-1").is_err());
+    }
+
+    #[test]
+    fn ignores_shift_only_for_characters() {
+        let upper = KeyChord::parse("G").unwrap();
+        assert!(upper.matches(&KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert!(upper.matches(&KeyEvent::new(KeyCode::Char('G'), KeyModifiers::NONE)));
+        assert!(!upper.matches(&KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)));
+
+        let ctrl = KeyChord::parse("ctrl-r").unwrap();
+        assert!(ctrl.matches(&KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+        assert!(!ctrl.matches(&KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)));
+
+        let up = KeyChord::parse("up").unwrap();
+        assert!(!up.matches(&KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT)));
+        assert!(KeyChord::parse("shift-up").unwrap().matches(&KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT)));
+    }
+}
@@ -1,24 +1,46 @@
-use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use colored::*;
-use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers, KeyEventKind},
-    execute,
-    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Alignment},
-    style::{Color, Style, Modifier},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Terminal,
-};
-use std::{
-    io::stdout,
-    process::{Command, Stdio},
-    time::Duration,
-};
+mod backend;
+mod clock;
+mod completions;
+mod config;
+mod daemon;
+mod desktop;
+mod duration;
+mod focus;
+mod frecency;
+mod history;
+mod hooks;
+mod keymap;
+mod manpage;
+mod pins;
+mod plan;
+mod profile;
+mod protect;
+mod restart;
+mod rules;
+mod schedule;
+mod size;
+mod snapshot;
+mod store;
+mod theme;
+mod tui;
+mod wait;
+mod watch;
+mod workspace;
+
+use anyhow::{bail, Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueHint};
+use dialoguer::Confirm;
+use std::{io::IsTerminal, path::PathBuf};
+
+use backend::{AppInfo, Backend, OpenMode, OpenOptions};
+use config::Config;
+use frecency::Frecency;
+use history::{KillHistory, KillRecord};
+use hooks::HookEvent;
+use pins::Pins;
+use protect::ProtectedApps;
+use theme::{ColorChoice, Role};
+use workspace::Workspace;
 
 // App icons for common applications
 const APP_ICONS: &[(&str, &str)] = &[
@@ -113,222 +135,515 @@ const APP_ICONS: &[(&str, &str)] = &[
 ];
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = "Examples:
+  qapp                     # interactive list of running applications
+  qapp open                # search installed applications
+  qapp kill Slack --yes
+  qapp completions zsh > ~/.zfunc/_qapp")]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// When to use colors (NO_COLOR is respected with `auto`)
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// List all open applications
+    #[command(after_long_help = "Examples:
+  qapp list
+  qapp list | grep -i slack")]
     List,
     /// Open an application
+    #[command(after_long_help = "Examples:
+  qapp open Safari
+  qapp open --new Terminal
+  qapp open Preview a.pdf b.pdf
+  qapp open Firefox --url https://example.com
+  qapp open \"Google Chrome\" --profile debug --args -- --incognito")]
     Open {
         /// The application name to open (without .app)
         name: Option<String>,
+        /// Launch a new instance even if the app is already running
+        #[arg(short, long, conflicts_with = "background")]
+        new: bool,
+        /// Launch without bringing the app to the front
+        #[arg(short = 'g', long)]
+        background: bool,
+        /// Files to open with the application
+        #[arg(requires = "name", value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
+        /// A URL to open with the application (repeatable)
+        #[arg(long = "url", value_name = "URL", requires = "name")]
+        urls: Vec<String>,
+        /// Arguments passed to the application, e.g. `--args -- --incognito`
+        #[arg(long, num_args = 0.., allow_hyphen_values = true, requires = "name")]
+        args: Vec<String>,
+        #[arg(last = true, hide = true, requires = "name")]
+        trailing_args: Vec<String>,
+        /// Launch profile from the app's `[apps.<name>.profiles]` config
+        #[arg(short, long)]
+        profile: Option<String>,
     },
     /// Kill (terminate) an application
+    #[command(after_long_help = "Examples:
+  qapp kill Slack
+  qapp kill Finder --yes --allow-protected
+  qapp kill Slack --in 25m # leave the daemon to quit it later
+  qapp undo                # relaunch what the last kill quit")]
     Kill {
         /// The application name to kill
         name: Option<String>,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Allow killing an app on the protected list
+        #[arg(long)]
+        allow_protected: bool,
+        /// Schedule the kill for this long from now instead (e.g. 25m)
+        #[arg(long = "in", value_name = "DURATION", value_parser = duration::parse, requires = "name")]
+        after: Option<std::time::Duration>,
+    },
+    /// Quit an application, waiting for it to exit, and open it again
+    #[command(after_long_help = "Examples:
+  qapp restart Slack
+  qapp restart Docker --yes --timeout 30s")]
+    Restart {
+        /// The application name to restart
+        name: String,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Allow restarting an app on the protected list
+        #[arg(long)]
+        allow_protected: bool,
+        /// Force quit if the app hasn't quit after this long (e.g. 10s)
+        #[arg(long, value_parser = duration::parse)]
+        timeout: Option<std::time::Duration>,
+    },
+    /// Hide an application
+    #[command(after_long_help = "Examples:
+  qapp hide Slack")]
+    Hide {
+        /// The application name to hide
+        name: String,
+    },
+    /// Show a hidden application again
+    #[command(alias = "show", after_long_help = "Examples:
+  qapp unhide Slack
+  qapp show Slack")]
+    Unhide {
+        /// The application name to show
+        name: String,
+    },
+    /// Hide every application except the given one
+    #[command(after_long_help = "Examples:
+  qapp hide-others Terminal")]
+    HideOthers {
+        /// The application name to keep visible
+        name: String,
+    },
+    /// Minimize all windows of an application
+    #[command(after_long_help = "Examples:
+  qapp minimize Safari")]
+    Minimize {
+        /// The application name to minimize
+        name: String,
+    },
+    /// Relaunch the applications quit by the most recent kill
+    #[command(after_long_help = "Examples:
+  qapp kill Slack
+  qapp undo                # Slack opens again")]
+    Undo,
+    /// Manage pinned (favorite) applications
+    #[command(after_long_help = "Examples:
+  qapp pin add Slack
+  qapp pin list
+  qapp pin rm Slack")]
+    Pin {
+        #[command(subcommand)]
+        action: PinAction,
+    },
+    /// Open the applications listed in the nearest .qapp.toml
+    #[command(after_long_help = "Examples:
+  cd ~/src/project && qapp up")]
+    Up,
+    /// Quit the applications listed in the nearest .qapp.toml
+    #[command(after_long_help = "Examples:
+  cd ~/src/project && qapp down")]
+    Down,
+    /// Open or quit a named set of applications
+    #[command(alias = "ws", after_long_help = "Examples:
+  qapp workspace list
+  qapp workspace up coding
+  qapp ws down coding")]
+    Workspace {
+        #[command(subcommand)]
+        action: WorkspaceAction,
+    },
+    /// Save and restore the set of running applications
+    #[command(after_long_help = "Examples:
+  qapp snapshot save before-demo
+  qapp snapshot diff before-demo
+  qapp snapshot restore before-demo")]
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Run the open, kill, restart and wait steps of a plan file in order
+    #[command(after_long_help = "Examples:
+  qapp run onboarding.toml
+  qapp run cleanup.toml --keep-going
+
+A plan file lists its steps as [[steps]] tables:
+
+  [[steps]]
+  open = \"Slack\"
+  only-if-installed = true
+  timeout = \"20s\"   # fail unless it's running within 20s
+
+  [[steps]]
+  kill = \"Zoom\"
+  only-if-running = true
+
+  [[steps]]
+  wait = \"Slack\"
+  until = \"running\"   # or \"exited\"")]
+    Run {
+        /// The plan file to run
+        #[arg(value_hint = ValueHint::FilePath)]
+        plan: PathBuf,
+        /// Carry on with the remaining steps after one fails
+        #[arg(long)]
+        keep_going: bool,
+    },
+    /// Wait until an application is running or has exited
+    #[command(after_long_help = "Examples:
+  qapp open Docker && qapp wait Docker --timeout 1m && docker ps
+  qapp kill Slack && qapp wait Slack --exited --timeout 10s
+
+Exits with a non-zero status if the timeout passes first.")]
+    Wait {
+        /// The application name to wait for
+        name: String,
+        /// Wait until it's running (the default)
+        #[arg(long, conflicts_with = "exited")]
+        running: bool,
+        /// Wait until it has exited
+        #[arg(long)]
+        exited: bool,
+        /// Give up after this long (e.g. 30s); waits for good without one
+        #[arg(long, value_parser = duration::parse)]
+        timeout: Option<std::time::Duration>,
+    },
+    /// Print a JSON line whenever an application starts or exits
+    #[command(after_long_help = "Examples:
+  qapp watch
+  qapp watch --existing --interval 5s | jq -r 'select(.event == \"exited\") | .name'")]
+    Watch {
+        /// How often to look at the running applications
+        #[arg(long, default_value = "1s", value_parser = duration::parse)]
+        interval: std::time::Duration,
+        /// Start with a started event for every app already running
+        #[arg(long)]
+        existing: bool,
+    },
+    /// Keep distracting applications closed for a while
+    #[command(args_conflicts_with_subcommands = true, after_long_help = "Examples:
+  qapp focus 50m --block Slack,Discord,Mail
+  qapp focus 2h --block Slack --restore
+  qapp focus status
+  qapp focus stop")]
+    Focus {
+        /// How long to keep the apps closed (e.g. 50m, 1h30m)
+        #[arg(value_parser = duration::parse, requires = "block")]
+        duration: Option<std::time::Duration>,
+        /// Applications to quit now and whenever they're launched
+        #[arg(long, value_delimiter = ',', value_name = "APPS")]
+        block: Vec<String>,
+        /// Reopen the quit applications when the time is up
+        #[arg(long)]
+        restore: bool,
+        #[command(subcommand)]
+        action: Option<FocusAction>,
+    },
+    /// Run a command at a time of day or after a delay
+    #[command(after_long_help = "Examples:
+  qapp at 18:00 kill Slack
+  qapp at 30m workspace down coding
+  qapp at 9:00 open Mail
+
+The daemon runs scheduled commands; see `qapp schedule list`.")]
+    At {
+        /// A time of day (HH:MM, the next one to come) or a delay (e.g. 30m)
+        when: String,
+        #[command(subcommand)]
+        command: schedule::ScheduledCommand,
+    },
+    /// List or cancel scheduled commands
+    #[command(after_long_help = "Examples:
+  qapp schedule list
+  qapp schedule rm 3")]
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    /// Inspect the rules the daemon applies
+    #[command(after_long_help = "Examples:
+  qapp rules check")]
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+    /// Serve app lists and actions to other qapp invocations over a socket
+    ///
+    /// Runs in the foreground, keeping the running and installed application
+    /// lists warm so searches and completions answer instantly. Other qapp
+    /// commands use the daemon automatically while it's running, and query
+    /// the system themselves when it isn't.
+    #[command(after_long_help = "Examples:
+  qapp daemon &
+  QAPP_NO_DAEMON=1 qapp kill Slack")]
+    Daemon,
+    /// Print a shell completion script
+    #[command(after_long_help = "Examples:
+  source <(qapp completions bash)
+  qapp completions fish > ~/.config/fish/completions/qapp.fish")]
+    Completions {
+        shell: completions::Shell,
+    },
+    /// Print the man page, or write pages for every command
+    #[command(after_long_help = "Examples:
+  qapp man | man -l -
+  qapp man --out-dir /usr/local/share/man/man1")]
+    Man {
+        /// Write qapp.1 and a page per subcommand into this directory
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        out_dir: Option<PathBuf>,
+    },
+    /// Print names for the completion scripts
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        source: completions::Source,
     },
 }
 
-enum Mode {
-    Normal,
-    Search,
+#[derive(Subcommand)]
+enum FocusAction {
+    /// Show the time left and the blocked applications
+    #[command(after_long_help = "Examples:
+  qapp focus status")]
+    Status,
+    /// End the session early
+    #[command(after_long_help = "Examples:
+  qapp focus stop")]
+    Stop,
 }
 
-enum ActionStatus {
-    None,
-    Opened(String),
-    Killed(String),
+#[derive(Subcommand)]
+enum ScheduleAction {
+    /// List pending commands and recurring schedules from the config file
+    #[command(after_long_help = "Examples:
+  qapp schedule list")]
+    List,
+    /// Cancel a pending command
+    #[command(alias = "remove", after_long_help = "Examples:
+  qapp schedule list
+  qapp schedule rm 3")]
+    Rm {
+        /// The id shown by `qapp schedule list`
+        id: u32,
+    },
 }
 
-struct AppState {
-    apps: Vec<String>,
-    installed_apps: Vec<String>,
-    filtered_apps: Vec<String>,
-    selected_index: usize,
-    mode: Mode,
-    search_query: String,
-    should_quit: bool,
-    action_status: ActionStatus,
-    status_counter: u8,
+#[derive(Subcommand)]
+enum RulesAction {
+    /// Show what every rule would do right now, without doing it
+    #[command(after_long_help = "Examples:
+  qapp rules check")]
+    Check,
 }
 
-impl AppState {
-    fn new(running_apps: Vec<String>) -> Self {
-        Self {
-            apps: running_apps,
-            installed_apps: Vec::new(),
-            filtered_apps: Vec::new(),
-            selected_index: 0,
-            mode: Mode::Normal,
-            search_query: String::new(),
-            should_quit: false,
-            action_status: ActionStatus::None,
-            status_counter: 0,
-        }
-    }
-    
-    fn set_opened(&mut self, app_name: String) {
-        self.action_status = ActionStatus::Opened(app_name);
-        self.status_counter = 30; // Show for ~3 seconds (at 100ms per frame)
-    }
-    
-    fn set_killed(&mut self, app_name: String) {
-        self.action_status = ActionStatus::Killed(app_name);
-        self.status_counter = 30; // Show for ~3 seconds
-    }
-    
-    fn update_status(&mut self) {
-        if self.status_counter > 0 {
-            self.status_counter -= 1;
-            if self.status_counter == 0 {
-                self.action_status = ActionStatus::None;
-            }
-        }
-    }
-
-    fn load_installed_apps(&mut self) -> Result<()> {
-        // Get list of installed applications
-        let output = Command::new("find")
-            .args(["/Applications", "-maxdepth", "2", "-name", "*.app"])
-            .output()
-            .context("Failed to list installed applications")?;
-
-        let output_str = String::from_utf8(output.stdout)
-            .context("Failed to parse find output")?;
-
-        self.installed_apps = output_str
-            .lines()
-            .map(|s| {
-                s.trim()
-                    .strip_prefix("/Applications/")
-                    .unwrap_or(s)
-                    .strip_suffix(".app")
-                    .unwrap_or(s)
-                    .to_string()
-            })
-            .collect();
-
-        self.filter_installed_apps();
-        Ok(())
-    }
-
-    fn filter_installed_apps(&mut self) {
-        if self.search_query.is_empty() {
-            self.filtered_apps = self.installed_apps.clone();
-        } else {
-            let query = self.search_query.to_lowercase();
-            self.filtered_apps = self.installed_apps
-                .iter()
-                .filter(|app| app.to_lowercase().contains(&query))
-                .cloned()
-                .collect();
-        }
-        // Reset selection if filtered list changes
-        if !self.filtered_apps.is_empty() {
-            self.selected_index = self.selected_index.min(self.filtered_apps.len() - 1);
-        } else {
-            self.selected_index = 0;
-        }
-    }
-
-    fn next(&mut self) {
-        let apps = match self.mode {
-            Mode::Normal => &self.apps,
-            Mode::Search => &self.filtered_apps,
-        };
-        
-        let len = apps.len();
-        if len > 0 {
-            self.selected_index = (self.selected_index + 1) % len;
-        }
-    }
-
-    fn previous(&mut self) {
-        let apps = match self.mode {
-            Mode::Normal => &self.apps,
-            Mode::Search => &self.filtered_apps,
-        };
-        
-        let len = apps.len();
-        if len > 0 {
-            self.selected_index = (self.selected_index + len - 1) % len;
-        }
-    }
-
-    fn selected_app(&self) -> Option<&String> {
-        match self.mode {
-            Mode::Normal => self.apps.get(self.selected_index),
-            Mode::Search => self.filtered_apps.get(self.selected_index),
-        }
-    }
-
-    fn add_to_search(&mut self, c: char) {
-        self.search_query.push(c);
-        self.filter_installed_apps();
-    }
-
-    fn backspace_search(&mut self) {
-        self.search_query.pop();
-        self.filter_installed_apps();
-    }
+#[derive(Subcommand)]
+enum PinAction {
+    /// Pin an application to the top of the search list
+    #[command(after_long_help = "Examples:
+  qapp pin add \"Visual Studio Code\"")]
+    Add {
+        /// The application name to pin
+        name: String,
+    },
+    /// Unpin an application
+    #[command(alias = "remove", after_long_help = "Examples:
+  qapp pin rm Slack")]
+    Rm {
+        /// The application name to unpin
+        name: String,
+    },
+    /// List pinned applications
+    #[command(after_long_help = "Examples:
+  qapp pin list")]
+    List,
+}
 
-    fn enter_search_mode(&mut self) -> Result<()> {
-        if self.installed_apps.is_empty() {
-            self.load_installed_apps()?;
-        }
-        
-        self.mode = Mode::Search;
-        self.search_query.clear();
-        self.filter_installed_apps();
-        self.selected_index = 0;
-        Ok(())
-    }
+#[derive(Subcommand)]
+enum WorkspaceAction {
+    /// Open a workspace's applications and quit the ones it replaces
+    #[command(after_long_help = "Examples:
+  qapp workspace up coding")]
+    Up {
+        /// The workspace name from the config file
+        name: String,
+    },
+    /// Quit a workspace's applications
+    #[command(after_long_help = "Examples:
+  qapp workspace down coding")]
+    Down {
+        /// The workspace name from the config file
+        name: String,
+    },
+    /// List configured workspaces
+    #[command(after_long_help = "Examples:
+  qapp workspace list")]
+    List,
+}
 
-    fn exit_search_mode(&mut self) {
-        self.mode = Mode::Normal;
-        self.search_query.clear();
-        self.selected_index = 0;
-    }
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Record the running applications
+    #[command(after_long_help = "Examples:
+  qapp snapshot save before-demo")]
+    Save {
+        /// The snapshot name
+        name: String,
+    },
+    /// Relaunch applications from the snapshot that aren't running
+    #[command(after_long_help = "Examples:
+  qapp snapshot restore before-demo")]
+    Restore {
+        /// The snapshot name
+        name: String,
+    },
+    /// Show applications started or quit since the snapshot
+    #[command(after_long_help = "Examples:
+  qapp snapshot diff before-demo")]
+    Diff {
+        /// The snapshot name
+        name: String,
+    },
+    /// List saved snapshots
+    #[command(after_long_help = "Examples:
+  qapp snapshot list")]
+    List,
+    /// Delete a snapshot
+    #[command(alias = "remove", after_long_help = "Examples:
+  qapp snapshot rm before-demo")]
+    Rm {
+        /// The snapshot name
+        name: String,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    backend::check_selection()?;
+    let config = Config::load()?;
+    theme::init(&config, cli.color)?;
+    profile::init(&config);
+    hooks::init(&config);
 
     match &cli.command {
-        Some(Commands::List) => interactive_app_list()?,
-        Some(Commands::Open { name }) => open_application(name)?,
-        Some(Commands::Kill { name }) => kill_application(name)?,
-        None => interactive_app_list()?,
+        Some(Commands::List) => tui::interactive_app_list(&config)?,
+        Some(Commands::Open { name, new, background, files, urls, args, trailing_args, profile }) => {
+            let mode = if *new {
+                OpenMode::NewInstance
+            } else if *background {
+                OpenMode::Background
+            } else {
+                OpenMode::Focus
+            };
+            let options = OpenOptions {
+                mode,
+                files: absolute_paths(files)?,
+                urls: urls.iter().map(|url| check_url(url)).collect::<Result<_>>()?,
+                // `--args -- -x` reaches us with the separator still in place
+                args: args.iter().skip_while(|arg| *arg == "--").chain(trailing_args).cloned().collect(),
+                profile: profile.clone(),
+                ..OpenOptions::default()
+            };
+            open_application(name, &options, &config)?
+        },
+        Some(Commands::Kill { name: Some(name), allow_protected, after: Some(after), .. }) => {
+            if !allow_protected && ProtectedApps::load(&config.kill).contains(name) {
+                bail!("{} is protected; pass --allow-protected to kill it", name);
+            }
+            let command = schedule::ScheduledCommand::Kill { name: name.clone(), allow_protected: *allow_protected };
+            schedule::add_in(*after, command)?
+        },
+        Some(Commands::Kill { name, yes, allow_protected, .. }) => {
+            kill_application(name, *yes, *allow_protected, &config)?
+        },
+        Some(Commands::Restart { name, yes, allow_protected, timeout }) => {
+            restart_application(name, *yes, *allow_protected, *timeout, &config)?
+        },
+        Some(Commands::Hide { name }) => change_visibility("Hiding:", name, |backend, name| backend.hide(name))?,
+        Some(Commands::Unhide { name }) => change_visibility("Showing:", name, |backend, name| backend.unhide(name))?,
+        Some(Commands::HideOthers { name }) => {
+            change_visibility("Hiding all but:", name, |backend, name| backend.hide_others(name))?
+        },
+        Some(Commands::Minimize { name }) => {
+            change_visibility("Minimizing:", name, |backend, name| backend.minimize(name))?
+        },
+        Some(Commands::Undo) => undo_last_kill()?,
+        Some(Commands::Pin { action }) => manage_pins(action)?,
+        Some(Commands::Workspace { action }) => manage_workspace(action, &config)?,
+        Some(Commands::Snapshot { action }) => match action {
+            SnapshotAction::Save { name } => snapshot::save(name)?,
+            SnapshotAction::Restore { name } => snapshot::restore(name)?,
+            SnapshotAction::Diff { name } => snapshot::diff(name)?,
+            SnapshotAction::List => snapshot::list()?,
+            SnapshotAction::Rm { name } => snapshot::remove(name)?,
+        },
+        Some(Commands::Up) => project_workspace(true, &config)?,
+        Some(Commands::Down) => project_workspace(false, &config)?,
+        Some(Commands::Run { plan, keep_going }) => plan::run(plan, *keep_going, &config)?,
+        Some(Commands::Wait { name, exited, timeout, .. }) => {
+            let state = if *exited { wait::Until::Exited } else { wait::Until::Running };
+            wait::run(name, state, *timeout)?
+        },
+        Some(Commands::Watch { interval, existing }) => watch::run(*interval, *existing)?,
+        Some(Commands::Daemon) => daemon::run(&config)?,
+        Some(Commands::Focus { duration, block, restore, action }) => match (action, duration) {
+            (Some(FocusAction::Status), _) => focus::status()?,
+            (Some(FocusAction::Stop), _) => focus::stop()?,
+            (None, Some(duration)) => focus::start(*duration, block, *restore, &config)?,
+            (None, None) => bail!("Give a duration and --block, or use `qapp focus status`"),
+        },
+        Some(Commands::Rules { action: RulesAction::Check }) => rules::check(&config)?,
+        Some(Commands::At { when, command }) => schedule::add(schedule::parse_when(when)?, command.clone())?,
+        Some(Commands::Schedule { action }) => match action {
+            ScheduleAction::List => schedule::list(&config)?,
+            ScheduleAction::Rm { id } => schedule::remove(*id)?,
+        },
+        Some(Commands::Completions { shell }) => {
+            print!("{}", completions::generate(*shell, &mut Cli::command()))
+        },
+        Some(Commands::Complete { source }) => completions::print_names(*source, &config)?,
+        Some(Commands::Man { out_dir }) => write_man_pages(out_dir.as_deref())?,
+        None => tui::interactive_app_list(&config)?,
     }
 
     Ok(())
 }
 
 fn get_running_applications() -> Result<Vec<String>> {
-    let output = Command::new("osascript")
-        .arg("-e")
-        .arg("tell application \"System Events\" to get name of (processes where background only is false)")
-        .output()
-        .context("Failed to execute osascript command")?;
-
-    let output_str = String::from_utf8(output.stdout)
-        .context("Failed to parse osascript output")?;
-
-    // Parse the AppleScript output format
-    let apps: Vec<String> = output_str
-        .trim()
-        .trim_matches(|c| c == '{' || c == '}')
-        .split(", ")
-        .map(|s| s.trim_matches('"').to_string())
-        .collect();
-
-    Ok(apps)
+    backend::current().running_app_names()
+}
+
+fn get_running_application_details() -> Result<Vec<AppInfo>> {
+    backend::current().running_apps()
 }
 
 fn get_app_icon(app_name: &str) -> &'static str {
@@ -340,534 +655,345 @@ fn get_app_icon(app_name: &str) -> &'static str {
     "📱" // Default icon for applications
 }
 
-fn interactive_app_list() -> Result<()> {
-    // Setup terminal
-    terminal::enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // Get running apps
-    let apps = get_running_applications()?;
-    
-    if apps.is_empty() {
-        // Clean up terminal
-        terminal::disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-        terminal.show_cursor()?;
-        
-        println!("{}", "No visible applications found.".yellow());
-        return Ok(());
-    }
-    
-    // Create app state
-    let mut app_state = AppState::new(apps);
-    
-    // Preload installed apps in the background
-    app_state.load_installed_apps()?;
-
-    // Application loop
-    while !app_state.should_quit {
-        terminal.draw(|frame| {
-            // Create layout for the UI
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(1)
-                .constraints([
-                    Constraint::Length(3),  // Header
-                    Constraint::Min(5),     // List of apps
-                    Constraint::Length(3),  // Footer
-                ])
-                .split(frame.area());
-
-            // Header - changes based on mode
-            let header_text = match app_state.mode {
-                Mode::Normal => "Running Applications",
-                Mode::Search => "Search Applications",
-            };
-            
-            let header_content = match app_state.mode {
-                Mode::Normal => Line::from(vec![
-                    Span::styled(
-                        header_text,
-                        Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
-                    )
-                ]),
-                Mode::Search => Line::from(vec![
-                    Span::styled(
-                        format!("{}: ", header_text),
-                        Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled(
-                        &app_state.search_query,
-                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled(
-                        "_",  // Cursor
-                        Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
-                    ),
-                ]),
-            };
-            
-            let header = Paragraph::new(header_content)
-                .alignment(Alignment::Center)
-                .block(Block::default().borders(Borders::ALL));
-            
-            frame.render_widget(header, chunks[0]);
-
-            // List of apps - changes based on mode
-            let app_items: Vec<ListItem> = match app_state.mode {
-                Mode::Normal => app_state.apps
-                    .iter()
-                    .enumerate()
-                    .map(|(i, app)| {
-                        let icon = get_app_icon(app);
-                        let content = Line::from(vec![
-                            Span::raw(format!("{} ", icon)),
-                            Span::styled(
-                                app.clone(), 
-                                Style::default().fg(if i == app_state.selected_index { 
-                                    Color::Yellow 
-                                } else { 
-                                    Color::White 
-                                })
-                            ),
-                        ]);
-                        ListItem::new(content)
-                    })
-                    .collect(),
-                Mode::Search => app_state.filtered_apps
-                    .iter()
-                    .enumerate()
-                    .map(|(i, app)| {
-                        let icon = get_app_icon(app);
-                        let content = Line::from(vec![
-                            Span::raw(format!("{} ", icon)),
-                            Span::styled(
-                                app.clone(), 
-                                Style::default().fg(if i == app_state.selected_index { 
-                                    Color::Yellow 
-                                } else { 
-                                    Color::White 
-                                })
-                            ),
-                        ]);
-                        ListItem::new(content)
-                    })
-                    .collect(),
-            };
-
-            let list_title = match app_state.mode {
-                Mode::Normal => "Running Applications",
-                Mode::Search => if app_state.filtered_apps.is_empty() {
-                    "No matching applications"
-                } else {
-                    "Matching Applications"
-                },
-            };
-
-            let apps_list = List::new(app_items)
-                .block(Block::default()
-                    .title(list_title)
-                    .borders(Borders::ALL))
-                .highlight_style(
-                    Style::default()
-                        .bg(Color::Blue)
-                        .fg(Color::White)
-                        .add_modifier(Modifier::BOLD),
-                )
-                .highlight_symbol("➤ ");
-
-            frame.render_stateful_widget(
-                apps_list,
-                chunks[1],
-                &mut ratatui::widgets::ListState::default().with_selected(Some(app_state.selected_index)),
-            );
-
-            // Footer with keybindings or status message
-            let footer_content = match app_state.action_status {
-                ActionStatus::None => {
-                    // Show normal keybindings
-                    let keybindings = match app_state.mode {
-                        Mode::Normal => "↑/↓: Navigate   O: Open   K: Kill   /: Search   Q: Quit",
-                        Mode::Search => "↑/↓: Navigate   Enter: Open   Esc: Cancel   Backspace: Delete",
-                    };
-                    
-                    Line::from(vec![
-                        Span::styled(
-                            keybindings, 
-                            Style::default().fg(Color::Yellow)
-                        )
-                    ])
-                },
-                ActionStatus::Opened(ref app_name) => {
-                    // Show opened confirmation
-                    Line::from(vec![
-                        Span::styled(
-                            "✅ ", 
-                            Style::default().fg(Color::Green)
-                        ),
-                        Span::styled(
-                            app_name.clone(),
-                            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
-                        ),
-                        Span::styled(
-                            " opened",
-                            Style::default().fg(Color::Green)
-                        )
-                    ])
-                },
-                ActionStatus::Killed(ref app_name) => {
-                    // Show killed confirmation
-                    Line::from(vec![
-                        Span::styled(
-                            "❌ ", 
-                            Style::default().fg(Color::Red)
-                        ),
-                        Span::styled(
-                            app_name.clone(),
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
-                        ),
-                        Span::styled(
-                            " terminated",
-                            Style::default().fg(Color::Red)
-                        )
-                    ])
-                },
-            };
-            
-            let footer = Paragraph::new(footer_content)
-                .alignment(Alignment::Left)
-                .block(Block::default().borders(Borders::ALL));
-            
-            frame.render_widget(footer, chunks[2]);
-        })?;
-
-        // Update action status counter
-        app_state.update_status();
-
-        // Handle input
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(KeyEvent { code, modifiers, kind, .. }) = event::read()? {
-                if kind == KeyEventKind::Press {
-                    match app_state.mode {
-                        Mode::Normal => match code {
-                            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-                                app_state.should_quit = true;
-                            },
-                            KeyCode::Up => {
-                                app_state.previous();
-                            },
-                            KeyCode::Down => {
-                                app_state.next();
-                            },
-                            KeyCode::Char('o') | KeyCode::Char('O') => {
-                                if let Some(app_name) = app_state.selected_app() {
-                                    let app_name_copy = app_name.clone();
-                                    
-                                    // Open the application
-                                    open_specific_application(app_name)?;
-                                    
-                                    // Update state with success message
-                                    app_state.set_opened(app_name_copy);
-                                    
-                                    // Refresh the list of running apps
-                                    if let Ok(updated_apps) = get_running_applications() {
-                                        app_state.apps = updated_apps;
-                                        app_state.selected_index = app_state.selected_index.min(app_state.apps.len().saturating_sub(1));
-                                    }
-                                }
-                            },
-                            KeyCode::Char('k') | KeyCode::Char('K') => {
-                                if let Some(app_name) = app_state.selected_app() {
-                                    let app_name_copy = app_name.clone();
-                                    
-                                    // Kill the application
-                                    kill_specific_application(app_name)?;
-                                    
-                                    // Update state with success message
-                                    app_state.set_killed(app_name_copy);
-                                    
-                                    // Refresh the list of running apps
-                                    if let Ok(updated_apps) = get_running_applications() {
-                                        app_state.apps = updated_apps;
-                                        app_state.selected_index = app_state.selected_index.min(app_state.apps.len().saturating_sub(1));
-                                    }
-                                }
-                            },
-                            KeyCode::Char('/') => {
-                                app_state.enter_search_mode()?;
-                            },
-                            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-                                app_state.should_quit = true;
-                            },
-                            _ => {}
-                        },
-                        Mode::Search => match code {
-                            KeyCode::Esc => {
-                                app_state.exit_search_mode();
-                            },
-                            KeyCode::Enter => {
-                                if let Some(app_name) = app_state.selected_app() {
-                                    let app_name_copy = app_name.clone();
-                                    
-                                    // Open the application
-                                    open_specific_application(app_name)?;
-                                    
-                                    // Update state with success message and exit search mode
-                                    app_state.set_opened(app_name_copy);
-                                    app_state.exit_search_mode();
-                                    
-                                    // Refresh the list of running apps
-                                    if let Ok(updated_apps) = get_running_applications() {
-                                        app_state.apps = updated_apps;
-                                        app_state.selected_index = app_state.selected_index.min(app_state.apps.len().saturating_sub(1));
-                                    }
-                                }
-                            },
-                            KeyCode::Backspace => {
-                                app_state.backspace_search();
-                            },
-                            KeyCode::Up => {
-                                app_state.previous();
-                            },
-                            KeyCode::Down => {
-                                app_state.next();
-                            },
-                            KeyCode::Char(c) => {
-                                if c == 'c' && modifiers.contains(KeyModifiers::CONTROL) {
-                                    app_state.should_quit = true;
-                                } else {
-                                    app_state.add_to_search(c);
-                                }
-                            },
-                            _ => {}
-                        },
-                    }
-                }
-            }
-        }
-    }
-
-    // Clean up terminal
-    terminal::disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-
-    Ok(())
+fn open_specific_application(app_name: &str, options: &OpenOptions) -> Result<()> {
+    open_application_target(app_name, app_name, options)
 }
 
-fn open_specific_application(app_name: &str) -> Result<()> {
+/// Opens `target`, an app name or bundle path, counting the launch for `app_name`.
+fn open_application_target(target: &str, app_name: &str, options: &OpenOptions) -> Result<()> {
     // No need to print here since we show status in the UI
-    Command::new("open")
-        .arg("-a")
-        .arg(app_name)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .context(format!("Failed to open application: {}", app_name))?;
+    focus::check_allowed(app_name)?;
+    let options = profile::apply(app_name, options)?;
+    hooks::run(HookEvent::BeforeOpen, app_name)?;
+    backend::current().open(target, &options)?;
+
+    // Ranking is best effort and must never stop an app from opening
+    let _ = Frecency::record_launch(app_name);
+    hooks::run(HookEvent::AfterOpen, app_name)?;
 
     Ok(())
 }
 
 fn kill_specific_application(app_name: &str) -> Result<()> {
     // No need to print here since we show status in the UI
-    Command::new("osascript")
-        .arg("-e")
-        .arg(format!("tell application \"{}\" to quit", app_name))
-        .output()
-        .context(format!("Failed to kill application: {}", app_name))?;
+    hooks::around(HookEvent::BeforeKill, HookEvent::AfterKill, app_name, || {
+        backend::current().quit(app_name)
+    })
+}
 
-    Ok(())
+fn force_kill_specific_application(app_name: &str) -> Result<()> {
+    hooks::around(HookEvent::BeforeKill, HookEvent::AfterKill, app_name, || {
+        backend::current().force_quit(app_name)
+    })
 }
 
-fn open_application(name: &Option<String>) -> Result<()> {
+fn open_application(name: &Option<String>, options: &OpenOptions, config: &Config) -> Result<()> {
     match name {
         Some(name) => {
+            if let Some(reopened) = focus::end_if_over()? {
+                focus::print_ended(&reopened);
+            }
+
             // When using from command line, print a message
-            println!("{} {}", "Opening:".green(), name.cyan());
-            open_specific_application(name)
+            let theme = theme::current();
+            let label = match options.mode {
+                OpenMode::Focus => "Opening:",
+                OpenMode::NewInstance => "Opening new instance:",
+                OpenMode::Background => "Opening in background:",
+            };
+            println!("{} {}", theme.paint(label, Role::Success), theme.paint(name, Role::Name));
+            open_specific_application(name, options)
         },
-        None => {
-            // Setup terminal for interactive search
-            terminal::enable_raw_mode()?;
-            let mut stdout = stdout();
-            execute!(stdout, EnterAlternateScreen)?;
-            let backend = CrosstermBackend::new(stdout);
-            let mut terminal = Terminal::new(backend)?;
-            
-            // Create app state in search mode
-            let mut app_state = AppState::new(vec![]);
-            app_state.load_installed_apps()?;
-            app_state.enter_search_mode()?;
-            
-            // Application loop
-            while !app_state.should_quit {
-                terminal.draw(|frame| {
-                    // Create layout for the UI
-                    let chunks = Layout::default()
-                        .direction(Direction::Vertical)
-                        .margin(2)
-                        .constraints([
-                            Constraint::Length(3),  // Header
-                            Constraint::Min(5),     // List of apps
-                            Constraint::Length(3),  // Footer
-                        ])
-                        .split(frame.area());
-        
-                    // Header with search
-                    let header = Paragraph::new(Line::from(vec![
-                        Span::styled(
-                            "Search Applications: ",
-                            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
-                        ),
-                        Span::styled(
-                            &app_state.search_query,
-                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                        ),
-                        Span::styled(
-                            "_",  // Cursor
-                            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
-                        ),
-                    ]))
-                    .alignment(Alignment::Center)
-                    .block(Block::default().borders(Borders::ALL));
-                    
-                    frame.render_widget(header, chunks[0]);
-        
-                    // List of matching apps
-                    let list_title = if app_state.filtered_apps.is_empty() {
-                        "No matching applications"
-                    } else {
-                        "Matching Applications"
-                    };
-                    
-                    let app_items: Vec<ListItem> = app_state.filtered_apps
-                        .iter()
-                        .enumerate()
-                        .map(|(i, app)| {
-                            let icon = get_app_icon(app);
-                            let content = Line::from(vec![
-                                Span::raw(format!("{} ", icon)),
-                                Span::styled(
-                                    app.clone(), 
-                                    Style::default().fg(if i == app_state.selected_index { 
-                                        Color::Yellow 
-                                    } else { 
-                                        Color::White 
-                                    })
-                                ),
-                            ]);
-                            ListItem::new(content)
-                        })
-                        .collect();
-        
-                    let apps_list = List::new(app_items)
-                        .block(Block::default()
-                            .title(list_title)
-                            .borders(Borders::ALL))
-                        .highlight_style(
-                            Style::default()
-                                .bg(Color::Blue)
-                                .fg(Color::White)
-                                .add_modifier(Modifier::BOLD),
-                        )
-                        .highlight_symbol("➤ ");
-        
-                    frame.render_stateful_widget(
-                        apps_list,
-                        chunks[1],
-                        &mut ratatui::widgets::ListState::default().with_selected(Some(app_state.selected_index)),
-                    );
-        
-                    // Footer with keybindings
-                    let footer = Paragraph::new(Line::from(vec![
-                        Span::styled(
-                            "↑/↓: Navigate   Enter: Open   Esc: Cancel   Backspace: Delete", 
-                            Style::default().fg(Color::Yellow)
-                        )
-                    ]))
-                    .alignment(Alignment::Center)
-                    .block(Block::default().borders(Borders::ALL));
-                    
-                    frame.render_widget(footer, chunks[2]);
-                })?;
-        
-                // Handle input
-                if event::poll(Duration::from_millis(100))? {
-                    if let Event::Key(KeyEvent { code, modifiers, kind, .. }) = event::read()? {
-                        if kind == KeyEventKind::Press {
-                            match code {
-                                KeyCode::Esc => {
-                                    app_state.should_quit = true;
-                                },
-                                KeyCode::Enter => {
-                                    if let Some(app_name) = app_state.selected_app() {
-                                        // Clean up terminal
-                                        terminal::disable_raw_mode()?;
-                                        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                                        terminal.show_cursor()?;
-                                        
-                                        open_specific_application(app_name)?;
-                                        return Ok(());
-                                    }
-                                },
-                                KeyCode::Backspace => {
-                                    app_state.backspace_search();
-                                },
-                                KeyCode::Up => {
-                                    app_state.previous();
-                                },
-                                KeyCode::Down => {
-                                    app_state.next();
-                                },
-                                KeyCode::Char(c) => {
-                                    if c == 'c' && modifiers.contains(KeyModifiers::CONTROL) {
-                                        app_state.should_quit = true;
-                                    } else {
-                                        app_state.add_to_search(c);
-                                    }
-                                },
-                                _ => {}
-                            }
-                        }
-                    }
-                }
+        None => tui::search_and_open(options, config),
+    }
+}
+
+/// Resolves documents against the current directory, since the app won't
+/// be started there.
+fn absolute_paths(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    files
+        .iter()
+        .map(|file| {
+            if !file.exists() {
+                bail!("No such file: {}", file.display());
             }
-            
-            // Clean up terminal
-            terminal::disable_raw_mode()?;
-            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-            terminal.show_cursor()?;
-            
-            Ok(())
-        }
+            std::path::absolute(file).context(format!("Failed to resolve path: {}", file.display()))
+        })
+        .collect()
+}
+
+fn check_url(url: &str) -> Result<String> {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme).unwrap_or_default();
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+
+    if !valid {
+        bail!("Not a URL: {}", url);
     }
+    Ok(url.to_string())
 }
 
-fn kill_application(name: &Option<String>) -> Result<()> {
+fn kill_application(name: &Option<String>, yes: bool, allow_protected: bool, config: &Config) -> Result<()> {
     match name {
         Some(name) => {
+            let theme = theme::current();
             let apps = get_running_applications()?;
             
             if apps.is_empty() {
-                println!("{}", "No running applications found.".yellow());
+                println!("{}", theme.paint("No running applications found.", Role::Warning));
                 return Ok(());
             }
             
             if !apps.contains(name) {
-                println!("{} {}", "Application not running:".red(), name.cyan());
+                println!("{} {}", theme.paint("Application not running:", Role::Danger), theme.paint(name, Role::Name));
                 return Ok(());
             }
             
+            if !allow_protected && ProtectedApps::load(&config.kill).contains(name) {
+                bail!("{} is protected; pass --allow-protected to kill it", name);
+            }
+
+            if config.kill.confirm && !yes && !confirm(&format!("Kill {}?", name))? {
+                return Ok(());
+            }
+
             // When using from command line, print a message
-            println!("{} {}", "Killing:".red(), name.cyan());
-            kill_specific_application(name)
+            println!("{} {}", theme.paint("Killing:", Role::Danger), theme.paint(name, Role::Name));
+            kill_specific_application(name)?;
+            KillHistory::record(std::slice::from_ref(name))
         },
         None => {
             // Use our interactive app list which already has the kill functionality
-            interactive_app_list()
+            tui::interactive_app_list(config)
         }
     }
 }
+
+fn restart_application(
+    name: &str,
+    yes: bool,
+    allow_protected: bool,
+    timeout: Option<std::time::Duration>,
+    config: &Config,
+) -> Result<()> {
+    let theme = theme::current();
+
+    if !allow_protected && ProtectedApps::load(&config.kill).contains(name) {
+        bail!("{} is protected; pass --allow-protected to restart it", name);
+    }
+
+    if config.kill.confirm && !yes && !confirm(&format!("Restart {}?", name))? {
+        return Ok(());
+    }
+
+    let escalate_after = timeout.unwrap_or(config.kill.escalate_after);
+    restart::restart(name, escalate_after, |phase| {
+        println!("{} {}", theme.paint(&format!("{}:", name), Role::Name), phase.describe());
+    })?;
+
+    println!("{} {}", theme.paint("Restarted:", Role::Success), theme.paint(name, Role::Name));
+    Ok(())
+}
+
+fn write_man_pages(out_dir: Option<&std::path::Path>) -> Result<()> {
+    let mut cmd = Cli::command();
+
+    let Some(dir) = out_dir else {
+        let (_, page) = manpage::pages(&mut cmd).swap_remove(0);
+        print!("{}", page);
+        return Ok(());
+    };
+
+    let theme = theme::current();
+    for path in manpage::write_all(&mut cmd, dir)? {
+        println!("{} {}", theme.paint("Wrote:", Role::Success), path);
+    }
+    Ok(())
+}
+
+fn change_visibility(label: &str, name: &str, change: impl Fn(&dyn Backend, &str) -> Result<()>) -> Result<()> {
+    let theme = theme::current();
+
+    if !get_running_applications()?.iter().any(|app| app == name) {
+        println!("{} {}", theme.paint("Application not running:", Role::Danger), theme.paint(name, Role::Name));
+        return Ok(());
+    }
+
+    println!("{} {}", theme.paint(label, Role::Success), theme.paint(name, Role::Name));
+    change(backend::current(), name)
+}
+
+fn undo_last_kill() -> Result<()> {
+    let theme = theme::current();
+
+    let Some(record) = KillHistory::pop()? else {
+        println!("{}", theme.paint("Nothing to undo.", Role::Warning));
+        return Ok(());
+    };
+
+    let mut failed = Vec::new();
+    for app_name in &record.apps {
+        println!(
+            "{} {} (quit {})",
+            theme.paint("Relaunching:", Role::Success),
+            theme.paint(app_name, Role::Name),
+            history::time_ago(record.killed_at),
+        );
+        if let Err(err) = open_specific_application(app_name, &OpenOptions::default()) {
+            println!("{} {} ({:#})", theme.paint("Failed:", Role::Danger), theme.paint(app_name, Role::Name), err);
+            failed.push(app_name.clone());
+        }
+    }
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    // Keep what couldn't be relaunched, so a later undo can try again
+    let count = failed.len();
+    KillHistory::restore(KillRecord { apps: failed, killed_at: record.killed_at })?;
+    bail!("{} application(s) couldn't be relaunched; run `qapp undo` again to retry", count)
+}
+
+fn manage_pins(action: &PinAction) -> Result<()> {
+    let theme = theme::current();
+    let mut pins = Pins::load()?;
+
+    match action {
+        PinAction::Add { name } => {
+            if pins.add(name)? {
+                println!("{} {}", theme.paint("Pinned:", Role::Success), theme.paint(name, Role::Name));
+            } else {
+                println!("{} {}", theme.paint("Already pinned:", Role::Warning), theme.paint(name, Role::Name));
+            }
+        },
+        PinAction::Rm { name } => {
+            if pins.remove(name)? {
+                println!("{} {}", theme.paint("Unpinned:", Role::Success), theme.paint(name, Role::Name));
+            } else {
+                println!("{} {}", theme.paint("Not pinned:", Role::Warning), theme.paint(name, Role::Name));
+            }
+        },
+        PinAction::List => {
+            if pins.apps().is_empty() {
+                println!("{}", theme.paint("No pinned applications.", Role::Warning));
+            }
+            for app_name in pins.apps() {
+                println!("{} {}", get_app_icon(app_name), app_name);
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn manage_workspace(action: &WorkspaceAction, config: &Config) -> Result<()> {
+    let theme = theme::current();
+
+    match action {
+        WorkspaceAction::Up { name } => {
+            println!("{} {}", theme.paint("Workspace up:", Role::Accent), theme.paint(name, Role::Name));
+            workspace::named(config, name)?.up(config)?.print();
+        },
+        WorkspaceAction::Down { name } => {
+            println!("{} {}", theme.paint("Workspace down:", Role::Accent), theme.paint(name, Role::Name));
+            workspace::named(config, name)?.down(config)?.print();
+        },
+        WorkspaceAction::List => {
+            if config.workspaces.is_empty() {
+                println!("{}", theme.paint("No workspaces configured.", Role::Warning));
+            }
+            for (name, workspace) in &config.workspaces {
+                let apps: Vec<&str> = workspace.apps.iter().map(|app| app.name.as_str()).collect();
+                println!("{}: {}", theme.paint(name, Role::Name), apps.join(", "));
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn project_workspace(up: bool, config: &Config) -> Result<()> {
+    let theme = theme::current();
+
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let Some(path) = workspace::find_project_file(&cwd) else {
+        bail!("No {} found in {} or its parents", workspace::PROJECT_FILE, cwd.display());
+    };
+
+    println!("{} {}", theme.paint("Using:", Role::Accent), theme.paint(&path.display().to_string(), Role::Name));
+    let project = Workspace::load(&path)?;
+
+    let summary = if up { project.up(config)? } else { project.down(config)? };
+    summary.print();
+    Ok(())
+}
+
+/// Asks a yes/no question, refusing when there is no terminal to ask on.
+fn confirm(prompt: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("{} Refusing without confirmation; pass --yes to skip the prompt", prompt);
+    }
+
+    Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .context("Failed to read confirmation")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.kill.protect_host = false;
+        config
+    }
+
+    #[test]
+    fn opening_counts_the_launch() {
+        let _sandbox = backend::sandbox();
+
+        open_specific_application("Slack", &OpenOptions::default()).unwrap();
+        assert!(get_running_applications().unwrap().contains(&"Slack".to_string()));
+        assert!(Frecency::load().unwrap().score("Slack", store::now()) > 0.0);
+    }
+
+    #[test]
+    fn refuses_to_kill_protected_apps() {
+        let _sandbox = backend::sandbox();
+
+        let err = kill_application(&Some("Finder".to_string()), true, false, &config()).unwrap_err();
+        assert!(err.to_string().contains("Finder is protected"), "{}", err);
+        assert!(get_running_applications().unwrap().contains(&"Finder".to_string()));
+
+        kill_application(&Some("Finder".to_string()), true, true, &config()).unwrap();
+        assert_eq!(get_running_applications().unwrap(), ["Terminal"]);
+    }
+
+    #[test]
+    fn undo_relaunches_the_last_kill() {
+        let _sandbox = backend::sandbox();
+
+        kill_application(&Some("Terminal".to_string()), true, false, &config()).unwrap();
+        assert_eq!(get_running_applications().unwrap(), ["Finder"]);
+
+        undo_last_kill().unwrap();
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Terminal"]);
+        assert!(KillHistory::pop().unwrap().is_none());
+    }
+
+    #[test]
+    fn undo_keeps_apps_it_couldnt_relaunch() {
+        let _sandbox = backend::sandbox();
+
+        let apps = ["Photoshop".to_string(), "Mail".to_string()];
+        KillHistory::record(&apps).unwrap();
+        assert!(undo_last_kill().is_err());
+
+        assert!(get_running_applications().unwrap().contains(&"Mail".to_string()));
+        assert_eq!(KillHistory::pop().unwrap().unwrap().apps, ["Photoshop"]);
+    }
+}
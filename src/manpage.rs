@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use clap::{Arg, Command};
+use std::{fs, path::Path};
+
+// Heading the examples in `after_long_help` start with
+const EXAMPLES_HEADING: &str = "Examples:";
+
+const ENVIRONMENT: &[(&str, &str)] = &[
+    ("QAPP_CONFIG", "Path of the config file, instead of $XDG_CONFIG_HOME/qapp/config.toml."),
+    ("XDG_CONFIG_HOME", "Base directory of the config file; ~/.config when unset."),
+    ("QAPP_DATA_DIR", "Directory for history, frecency, pins and snapshots, instead of $XDG_DATA_HOME/qapp."),
+    ("XDG_DATA_HOME", "Base directory of the data files; ~/.local/share when unset."),
+    ("QAPP_BACKEND", "Set to fake to use a simulated list of applications instead of the real ones, in builds with the fake-backend feature."),
+    ("QAPP_NO_DAEMON", "Talk to the system directly even when a qapp daemon is running."),
+    ("NO_COLOR", "Disables colors when set to a non-empty value, unless --color always is given."),
+    ("TERM_PROGRAM", "Identifies the terminal qapp runs in, which is protected from kills."),
+];
+
+const EXIT_STATUS: &[(&str, &str)] = &[
+    ("0", "Success."),
+    ("1", "The command failed; the reason is printed to standard error."),
+    ("2", "The command line could not be parsed."),
+];
+
+/// Escapes text for use in a roff document.
+fn escape(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.replace('\\', "\\e").replace('-', "\\-");
+            // Lines starting with these would be read as requests
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn page_name(path: &[&str]) -> String {
+    path.join("-")
+}
+
+fn option_label(arg: &Arg) -> String {
+    let mut names = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(format!("\\fB\\-{}\\fR", short));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+
+    let mut label = names.join(", ");
+    if arg.get_num_args().is_some_and(|range| range.takes_values()) {
+        let value = arg
+            .get_value_names()
+            .and_then(|names| names.first())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+        label.push_str(&format!(" \\fI{}\\fR", escape(&value)));
+    }
+    label
+}
+
+fn argument_help(arg: &Arg) -> String {
+    let mut help = arg
+        .get_long_help()
+        .or(arg.get_help())
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+
+    let values: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if arg.get_num_args().is_some_and(|range| range.takes_values()) && !values.is_empty() {
+        help.push_str(&format!(" [possible values: {}]", values.join(", ")));
+    }
+
+    escape(&help)
+}
+
+fn tagged(label: &str, body: &str) -> String {
+    format!(".TP\n{}\n{}\n", label, body)
+}
+
+/// The page for one (sub)command, `path` being its words after `qapp`.
+fn render(cmd: &Command, path: &[&str], version: &str) -> String {
+    let name = page_name(path);
+    let about = cmd.get_about().map(|about| about.to_string()).unwrap_or_default();
+
+    let mut page = format!(".TH {} 1 \"\" \"{}\" \"User Commands\"\n", escape(&name.to_uppercase()), version);
+    page.push_str(&format!(".SH NAME\n{} \\- {}\n", escape(&name), escape(&about)));
+
+    let usage = cmd.clone().render_usage().to_string();
+    let usage = usage.trim_start_matches("Usage:").trim();
+    page.push_str(&format!(".SH SYNOPSIS\n\\fB{}\\fR\n", escape(usage)));
+
+    let description = cmd.get_long_about().map(|about| about.to_string()).unwrap_or(about);
+    page.push_str(&format!(".SH DESCRIPTION\n{}\n", escape(&description)));
+
+    let arguments: Vec<&Arg> = cmd.get_positionals().filter(|arg| !arg.is_hide_set()).collect();
+    if !arguments.is_empty() {
+        page.push_str(".SH ARGUMENTS\n");
+        for arg in arguments {
+            let value = arg.get_value_names().and_then(|names| names.first()).map(|name| name.to_string());
+            let label = value.unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+            page.push_str(&tagged(&format!("\\fI{}\\fR", escape(&label)), &argument_help(arg)));
+        }
+    }
+
+    let options: Vec<&Arg> = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .collect();
+    if !options.is_empty() {
+        page.push_str(".SH OPTIONS\n");
+        for arg in options {
+            page.push_str(&tagged(&option_label(arg), &argument_help(arg)));
+        }
+    }
+
+    let subcommands: Vec<&Command> = cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+        .collect();
+    if !subcommands.is_empty() {
+        page.push_str(".SH COMMANDS\n");
+        for sub in &subcommands {
+            let mut sub_path = path.to_vec();
+            sub_path.push(sub.get_name());
+            let about = sub.get_about().map(|about| about.to_string()).unwrap_or_default();
+            page.push_str(&tagged(&format!("\\fB{}\\fR(1)", escape(&page_name(&sub_path))), &escape(&about)));
+        }
+    }
+
+    if let Some(help) = cmd.get_after_long_help().map(|help| help.to_string())
+        && let Some(examples) = help.trim().strip_prefix(EXAMPLES_HEADING)
+    {
+        page.push_str(".SH EXAMPLES\n.nf\n.RS 4\n");
+        for line in examples.trim_matches('\n').lines() {
+            page.push_str(&escape(line.strip_prefix("  ").unwrap_or(line)));
+            page.push('\n');
+        }
+        page.push_str(".RE\n.fi\n");
+    }
+
+    // Shared sections only go on the main page
+    if path.len() == 1 {
+        page.push_str(".SH ENVIRONMENT\n");
+        for (name, description) in ENVIRONMENT {
+            page.push_str(&tagged(&format!("\\fB{}\\fR", escape(name)), &escape(description)));
+        }
+
+        page.push_str(".SH EXIT STATUS\n");
+        for (code, description) in EXIT_STATUS {
+            page.push_str(&tagged(code, &escape(description)));
+        }
+    }
+
+    if path.len() > 1 {
+        let parent = page_name(&path[..path.len() - 1]);
+        page.push_str(&format!(".SH SEE ALSO\n\\fB{}\\fR(1)\n", escape(&parent)));
+    }
+
+    page
+}
+
+fn collect(cmd: &Command, path: Vec<&str>, version: &str, pages: &mut Vec<(String, String)>) {
+    pages.push((format!("{}.1", page_name(&path)), render(cmd, &path, version)));
+
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help") {
+        let mut sub_path = path.clone();
+        sub_path.push(sub.get_name());
+        collect(sub, sub_path, version, pages);
+    }
+}
+
+/// Every page, as `(file name, roff source)`, starting with `qapp.1`.
+pub fn pages(cmd: &mut Command) -> Vec<(String, String)> {
+    // Building fills in usage strings and propagates global options
+    cmd.build();
+
+    let version = format!("qapp {}", cmd.get_version().unwrap_or_default());
+    let mut pages = Vec::new();
+    collect(cmd, vec!["qapp"], &version, &mut pages);
+    pages
+}
+
+/// Writes every page into `dir`, returning their paths.
+pub fn write_all(cmd: &mut Command, dir: &Path) -> Result<Vec<String>> {
+    fs::create_dir_all(dir).context(format!("Failed to create directory: {}", dir.display()))?;
+
+    pages(cmd)
+        .into_iter()
+        .map(|(file, contents)| {
+            let path = dir.join(file);
+            fs::write(&path, contents).context(format!("Failed to write man page: {}", path.display()))?;
+            Ok(path.display().to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    use crate::Cli;
+
+    // Checks the page for `cmd` and its subcommands, returning how many pages that covers
+    fn check(cmd: &Command, path: Vec<&str>, pages: &[(String, String)]) -> usize {
+        let name = page_name(&path);
+        let (_, page) = pages
+            .iter()
+            .find(|(file, _)| *file == format!("{}.1", name))
+            .unwrap_or_else(|| panic!("no page for {}", name));
+
+        for arg in cmd.get_arguments().filter(|arg| !arg.is_hide_set()) {
+            let mut labels = Vec::new();
+            if let Some(long) = arg.get_long() {
+                labels.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+            }
+            if let Some(short) = arg.get_short() {
+                labels.push(format!("\\fB\\-{}\\fR", short));
+            }
+            if arg.is_positional() {
+                let value = arg.get_value_names().and_then(|names| names.first()).map(|name| name.to_string());
+                labels.push(format!("\\fI{}\\fR", value.unwrap_or_else(|| arg.get_id().as_str().to_uppercase())));
+            }
+            for label in labels {
+                assert!(page.contains(&label), "{} misses {}", name, label);
+            }
+        }
+
+        let subcommands: Vec<&Command> =
+            cmd.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help").collect();
+        if path.len() > 1 {
+            assert!(page.contains(".SH EXAMPLES\n"), "{} has no examples", name);
+        }
+        let mut checked = 1;
+        for sub in subcommands {
+            let mut sub_path = path.clone();
+            sub_path.push(sub.get_name());
+            let link = format!("\\fB{}\\fR(1)", escape(&page_name(&sub_path)));
+            assert!(page.contains(&link), "{} doesn't list {}", name, link);
+            checked += check(sub, sub_path, pages);
+        }
+        checked
+    }
+
+    #[test]
+    fn documents_every_command_and_option() {
+        let mut cmd = Cli::command();
+        let pages = pages(&mut cmd);
+        // Nothing beyond the visible commands gets a page
+        assert_eq!(check(&cmd, vec!["qapp"], &pages), pages.len());
+    }
+
+    #[test]
+    fn starts_with_the_main_page() {
+        let pages = pages(&mut Cli::command());
+        let (file, page) = &pages[0];
+        assert_eq!(file, "qapp.1");
+        assert!(page.starts_with(".TH QAPP 1 "));
+        assert!(page.contains(".SH ENVIRONMENT\n"));
+        assert!(!pages[1].1.contains(".SH ENVIRONMENT\n"));
+    }
+
+    #[test]
+    fn escapes_roff_syntax() {
+        assert_eq!(escape("--yes"), "\\-\\-yes");
+        assert_eq!(escape(r"C:\path"), r"C:\epath");
+        assert_eq!(escape(".qapp.toml\n'quoted'"), "\\&.qapp.toml\n\\&'quoted'");
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::store;
+
+const PINS_FILE: &str = "pins.json";
+
+/// Favorite apps, kept at the top of the search list in pinning order.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Pins {
+    apps: Vec<String>,
+}
+
+impl Pins {
+    pub fn load() -> Result<Self> {
+        store::load(PINS_FILE)
+    }
+
+    /// Changes the stored pins while holding their lock, so concurrent
+    /// edits aren't lost, and picks up the result here.
+    fn update<R>(&mut self, change: impl FnOnce(&mut Self) -> R) -> Result<R> {
+        let (result, apps) = store::update(PINS_FILE, |pins: &mut Self| {
+            let result = change(pins);
+            Ok((result, pins.apps.clone()))
+        })?;
+
+        self.apps = apps;
+        Ok(result)
+    }
+
+    pub fn apps(&self) -> &[String] {
+        &self.apps
+    }
+
+    pub fn contains(&self, app_name: &str) -> bool {
+        self.apps.iter().any(|app| app.eq_ignore_ascii_case(app_name))
+    }
+
+    /// Pins an app, returning false if it already was.
+    pub fn add(&mut self, app_name: &str) -> Result<bool> {
+        self.update(|pins| pins.pin(app_name))
+    }
+
+    /// Unpins an app, returning false if it wasn't pinned.
+    pub fn remove(&mut self, app_name: &str) -> Result<bool> {
+        self.update(|pins| pins.unpin(app_name))
+    }
+
+    /// Pins or unpins an app, returning whether it is now pinned.
+    pub fn toggle(&mut self, app_name: &str) -> Result<bool> {
+        self.update(|pins| !pins.unpin(app_name) && pins.pin(app_name))
+    }
+
+    fn pin(&mut self, app_name: &str) -> bool {
+        if self.contains(app_name) {
+            return false;
+        }
+
+        self.apps.push(app_name.to_string());
+        true
+    }
+
+    fn unpin(&mut self, app_name: &str) -> bool {
+        let len = self.apps.len();
+        self.apps.retain(|app| !app.eq_ignore_ascii_case(app_name));
+        self.apps.len() != len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend;
+
+    #[test]
+    fn edits_from_stale_copies_are_kept() {
+        let _sandbox = backend::sandbox();
+        let mut list = Pins::load().unwrap();
+        let mut picker = Pins::load().unwrap();
+
+        assert!(list.add("Slack").unwrap());
+        assert!(picker.add("Mail").unwrap());
+        assert!(!picker.add("slack").unwrap());
+        assert_eq!(Pins::load().unwrap().apps(), ["Slack", "Mail"]);
+
+        assert!(!list.toggle("Mail").unwrap());
+        assert!(list.toggle("Notes").unwrap());
+        assert_eq!(list.apps(), ["Slack", "Notes"]);
+        assert!(!list.remove("Mail").unwrap());
+    }
+}
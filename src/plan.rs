@@ -0,0 +1,375 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{self, OpenOptions},
+    config::Config,
+    duration,
+    get_running_applications,
+    history::KillHistory,
+    kill_specific_application, open_specific_application,
+    protect::ProtectedApps,
+    restart,
+    theme::{self, Role},
+    wait::{self, Until},
+};
+
+// How long a `wait` step waits when it doesn't give a timeout
+const DEFAULT_WAIT: Duration = Duration::from_secs(30);
+
+/// An ordered list of steps, read from a TOML file for `qapp run`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Plan {
+    #[serde(default)]
+    steps: Vec<Step>,
+}
+
+impl Plan {
+    fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+
+        toml::from_str(&contents).context(format!("Failed to parse {}", path.display()))
+    }
+}
+
+#[derive(Debug)]
+enum Action {
+    Open,
+    Kill,
+    Restart,
+    Wait(Until),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "RawStep")]
+struct Step {
+    action: Action,
+    name: String,
+    only_if_running: bool,
+    only_if_installed: bool,
+    /// How long the app may take to start or exit; for restarts, how long
+    /// before escalating to a force quit
+    timeout: Option<Duration>,
+    allow_protected: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct RawStep {
+    open: Option<String>,
+    kill: Option<String>,
+    restart: Option<String>,
+    wait: Option<String>,
+    until: Option<Until>,
+    #[serde(default)]
+    only_if_running: bool,
+    #[serde(default)]
+    only_if_installed: bool,
+    timeout: Option<String>,
+    #[serde(default)]
+    allow_protected: bool,
+}
+
+impl TryFrom<RawStep> for Step {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawStep) -> Result<Self> {
+        let mut actions = [
+            raw.open.map(|name| (Action::Open, name)),
+            raw.kill.map(|name| (Action::Kill, name)),
+            raw.restart.map(|name| (Action::Restart, name)),
+            raw.wait.map(|name| (Action::Wait(raw.until.unwrap_or(Until::Running)), name)),
+        ]
+        .into_iter()
+        .flatten();
+
+        let (Some((action, name)), None) = (actions.next(), actions.next()) else {
+            bail!("A step needs exactly one of open, kill, restart or wait");
+        };
+        if raw.until.is_some() && !matches!(action, Action::Wait(_)) {
+            bail!("`until` only applies to wait steps");
+        }
+
+        Ok(Self {
+            action,
+            name,
+            only_if_running: raw.only_if_running,
+            only_if_installed: raw.only_if_installed,
+            timeout: raw.timeout.as_deref().map(duration::parse).transpose()?,
+            allow_protected: raw.allow_protected,
+        })
+    }
+}
+
+enum Outcome {
+    Done(Duration),
+    Skipped(String),
+    Failed(String),
+}
+
+impl Step {
+    /// E.g. `open Slack` or `wait for Slack to be exited`.
+    fn describe(&self) -> String {
+        match self.action {
+            Action::Open => format!("open {}", self.name),
+            Action::Kill => format!("kill {}", self.name),
+            Action::Restart => format!("restart {}", self.name),
+            Action::Wait(state) => format!("wait for {} to be {}", self.name, state.describe()),
+        }
+    }
+
+    /// Runs the step, returning why it was skipped if a condition didn't hold.
+    fn run(&self, config: &Config) -> Result<Option<String>> {
+        let running = get_running_applications()?.iter().any(|app| app.eq_ignore_ascii_case(&self.name));
+
+        if self.only_if_running && !running {
+            return Ok(Some("not running".to_string()));
+        }
+        if self.only_if_installed
+            && !backend::current().installed_apps()?.iter().any(|app| app.eq_ignore_ascii_case(&self.name))
+        {
+            return Ok(Some("not installed".to_string()));
+        }
+
+        let protected = !self.allow_protected && ProtectedApps::load(&config.kill).contains(&self.name);
+        match self.action {
+            Action::Open => {
+                open_specific_application(&self.name, &OpenOptions::default())?;
+                self.wait_until(Until::Running, self.timeout)?;
+            },
+            Action::Kill => {
+                if !running {
+                    return Ok(Some("not running".to_string()));
+                }
+                if protected {
+                    bail!("{} is protected; set allow-protected on the step to kill it", self.name);
+                }
+                kill_specific_application(&self.name)?;
+                KillHistory::record(std::slice::from_ref(&self.name))?;
+                self.wait_until(Until::Exited, self.timeout)?;
+            },
+            Action::Restart => {
+                if protected {
+                    bail!("{} is protected; set allow-protected on the step to restart it", self.name);
+                }
+                let escalate_after = self.timeout.unwrap_or(config.kill.escalate_after);
+                restart::restart(&self.name, escalate_after, |_| {})?;
+            },
+            Action::Wait(state) => self.wait_until(state, Some(self.timeout.unwrap_or(DEFAULT_WAIT)))?,
+        }
+        Ok(None)
+    }
+
+    /// Fails if the app doesn't reach `state` within `timeout`; does nothing
+    /// without one.
+    fn wait_until(&self, state: Until, timeout: Option<Duration>) -> Result<()> {
+        match timeout {
+            Some(timeout) => wait::expect(&self.name, state, timeout),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Runs the steps of a plan file in order and prints what each one did.
+/// Stops at the first failing step unless `keep_going` is set.
+pub fn run(path: &Path, keep_going: bool, config: &Config) -> Result<()> {
+    let theme = theme::current();
+    let plan = Plan::load(path)?;
+    if plan.steps.is_empty() {
+        bail!("{} has no steps", path.display());
+    }
+
+    let total = plan.steps.len();
+    let mut outcomes = Vec::new();
+    for (index, step) in plan.steps.iter().enumerate() {
+        println!("{} {}", theme.paint(&format!("[{}/{}]", index + 1, total), Role::Accent), step.describe());
+
+        let started = Instant::now();
+        let outcome = match step.run(config) {
+            Ok(None) => Outcome::Done(started.elapsed()),
+            Ok(Some(reason)) => Outcome::Skipped(reason),
+            Err(err) => Outcome::Failed(format!("{:#}", err)),
+        };
+        let failed = matches!(outcome, Outcome::Failed(_));
+        outcomes.push(outcome);
+
+        if failed && !keep_going {
+            break;
+        }
+    }
+
+    println!();
+    println!("{}", theme.paint("Report:", Role::Accent));
+    for (index, step) in plan.steps.iter().enumerate() {
+        let (label, role, detail) = match outcomes.get(index) {
+            Some(Outcome::Done(took)) => ("done", Role::Success, duration::format(*took)),
+            Some(Outcome::Skipped(reason)) => ("skipped", Role::Warning, reason.clone()),
+            Some(Outcome::Failed(err)) => ("failed", Role::Danger, err.clone()),
+            None => ("not run", Role::Hint, String::new()),
+        };
+        let detail = if detail.is_empty() { String::new() } else { format!(" ({})", detail) };
+        println!("  {} {}{}", theme.paint(&format!("{:<8}", label), role), step.describe(), detail);
+    }
+
+    let failed = outcomes.iter().filter(|outcome| matches!(outcome, Outcome::Failed(_))).count();
+    if failed > 0 {
+        bail!("{} of {} steps failed", failed, total);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store;
+
+    fn step(toml: &str) -> Result<Step> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.kill.protect_host = false;
+        config
+    }
+
+    fn run_plan(toml: &str, keep_going: bool) -> Result<()> {
+        let path = store::data_dir()?.join("plan.toml");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, toml)?;
+        run(&path, keep_going, &config())
+    }
+
+    #[test]
+    fn reads_one_action_per_step() {
+        let open = step(
+            r#"
+            open = "Slack"
+            timeout = "10s"
+            only-if-installed = true
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(open.action, Action::Open));
+        assert_eq!(open.timeout, Some(Duration::from_secs(10)));
+        assert!(open.only_if_installed && !open.only_if_running);
+
+        assert!(matches!(step(r#"wait = "Slack""#).unwrap().action, Action::Wait(Until::Running)));
+        let exited = step(
+            r#"
+            wait = "Slack"
+            until = "exited"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(exited.describe(), "wait for Slack to be exited");
+    }
+
+    #[test]
+    fn rejects_steps_that_dont_say_one_thing() {
+        let error = |toml: &str| step(toml).unwrap_err().to_string();
+
+        assert!(error("only-if-running = true").contains("exactly one of open, kill, restart or wait"));
+        let both = r#"
+            open = "Slack"
+            kill = "Zoom"
+            "#;
+        assert!(error(both).contains("exactly one of open, kill, restart or wait"));
+        let until = r#"
+            kill = "Slack"
+            until = "exited"
+            "#;
+        assert!(error(until).contains("`until` only applies to wait steps"));
+        let timeout = r#"
+            open = "Slack"
+            timeout = "soon"
+            "#;
+        assert!(error(timeout).contains("Invalid duration 'soon'"));
+        assert!(step(r#"launch = "Slack""#).is_err());
+    }
+
+    #[test]
+    fn skips_steps_whose_conditions_dont_hold() {
+        let _sandbox = backend::sandbox();
+        let config = config();
+        let skipped = |toml: &str| step(toml).unwrap().run(&config).unwrap();
+
+        let not_running = r#"
+            restart = "Slack"
+            only-if-running = true
+            "#;
+        assert_eq!(skipped(not_running).as_deref(), Some("not running"));
+        let not_installed = r#"
+            open = "Photoshop"
+            only-if-installed = true
+            "#;
+        assert_eq!(skipped(not_installed).as_deref(), Some("not installed"));
+        assert_eq!(skipped(r#"kill = "Slack""#).as_deref(), Some("not running"));
+        assert_eq!(skipped(r#"open = "Slack""#), None);
+        assert!(get_running_applications().unwrap().contains(&"Slack".to_string()));
+    }
+
+    #[test]
+    fn kills_protected_apps_only_when_allowed() {
+        let _sandbox = backend::sandbox();
+        let config = config();
+
+        let err = step(r#"kill = "Finder""#).unwrap().run(&config).unwrap_err();
+        assert_eq!(err.to_string(), "Finder is protected; set allow-protected on the step to kill it");
+
+        let allowed = r#"
+            kill = "Finder"
+            allow-protected = true
+            "#;
+        assert_eq!(step(allowed).unwrap().run(&config).unwrap(), None);
+        assert_eq!(get_running_applications().unwrap(), ["Terminal"]);
+        assert_eq!(KillHistory::pop().unwrap().unwrap().apps, ["Finder"]);
+    }
+
+    #[test]
+    fn fails_a_kill_that_doesnt_finish_in_time() {
+        let _sandbox = backend::sandbox();
+        backend::hang("Terminal");
+
+        let slow = r#"
+            kill = "Terminal"
+            timeout = "0s"
+            "#;
+        let err = step(slow).unwrap().run(&config()).unwrap_err();
+        assert_eq!(err.to_string(), "Terminal still running after 0ms");
+    }
+
+    #[test]
+    fn stops_at_the_first_failure_unless_told_to_keep_going() {
+        let plan = r#"
+            [[steps]]
+            open = "Slack"
+
+            [[steps]]
+            open = "Photoshop"
+
+            [[steps]]
+            open = "Mail"
+            "#;
+
+        let sandbox = backend::sandbox();
+        let err = run_plan(plan, false).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 3 steps failed");
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Terminal", "Slack"]);
+        drop(sandbox);
+
+        let _sandbox = backend::sandbox();
+        let err = run_plan(plan, true).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 3 steps failed");
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Terminal", "Slack", "Mail"]);
+
+        let err = run_plan("steps = []", false).unwrap_err();
+        assert!(err.to_string().ends_with("plan.toml has no steps"), "{}", err);
+    }
+}
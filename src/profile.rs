@@ -0,0 +1,172 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use crate::{
+    backend::{OpenMode, OpenOptions},
+    config::Config,
+};
+
+/// Profile applied to every open of an app when none is asked for.
+pub const DEFAULT_PROFILE: &str = "default";
+
+static APPS: OnceLock<BTreeMap<String, AppConfig>> = OnceLock::new();
+
+/// Per-app settings from the `[apps.<name>]` config table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub profiles: BTreeMap<String, LaunchProfile>,
+}
+
+/// How to launch an app, from `[apps.<name>.profiles.<profile>]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LaunchProfile {
+    pub env: BTreeMap<String, String>,
+    /// Put before any arguments given on the command line
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub new_instance: bool,
+}
+
+impl LaunchProfile {
+    fn apply(&self, options: &mut OpenOptions) {
+        options.env.extend(self.env.iter().map(|(key, value)| (key.clone(), value.clone())));
+        options.args.splice(0..0, self.args.iter().cloned());
+
+        if let Some(cwd) = &self.cwd {
+            options.cwd = Some(expand_home(cwd));
+        }
+        if self.new_instance && options.mode == OpenMode::Focus {
+            options.mode = OpenMode::NewInstance;
+        }
+    }
+}
+
+/// Makes the configured profiles available to every open.
+pub fn init(config: &Config) {
+    let _ = APPS.set(config.apps.clone());
+}
+
+/// Adds the profile named in `options`, or the app's default profile, to
+/// the options.
+pub fn apply(app_name: &str, options: &OpenOptions) -> Result<OpenOptions> {
+    match APPS.get() {
+        Some(apps) => apply_from(apps, app_name, options),
+        None => Ok(options.clone()),
+    }
+}
+
+fn apply_from(apps: &BTreeMap<String, AppConfig>, app_name: &str, options: &OpenOptions) -> Result<OpenOptions> {
+    let mut options = options.clone();
+
+    let profile = match options.profile.as_deref() {
+        Some(name) => match find(apps, app_name, name) {
+            Some(profile) => profile,
+            None => bail!("No launch profile '{}' for {}", name, app_name),
+        },
+        None => match find(apps, app_name, DEFAULT_PROFILE) {
+            Some(profile) => profile,
+            None => return Ok(options),
+        },
+    };
+
+    profile.apply(&mut options);
+    Ok(options)
+}
+
+/// Looks a profile up under the app's config table, preferring the table
+/// whose name matches exactly over ones differing only in case.
+fn find<'a>(apps: &'a BTreeMap<String, AppConfig>, app_name: &str, profile: &str) -> Option<&'a LaunchProfile> {
+    let exact = apps.get(app_name).into_iter();
+    let loose = apps
+        .iter()
+        .filter(|(name, _)| name.as_str() != app_name && name.eq_ignore_ascii_case(app_name))
+        .map(|(_, app)| app);
+
+    exact.chain(loose).find_map(|app| app.profiles.get(profile))
+}
+
+fn expand_home(path: &std::path::Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apps() -> BTreeMap<String, AppConfig> {
+        let config: Config = toml::from_str(
+            r#"
+            [apps.Code.profiles.default]
+            args = ["--new-window"]
+
+            [apps.Code.profiles.work]
+            env = { PROFILE = "work" }
+            args = ["--profile", "work"]
+            cwd = "~/work"
+            new-instance = true
+
+            [apps.code.profiles.work]
+            args = ["--lowercase"]
+
+            [apps.slack.profiles.quiet]
+            args = ["--quiet"]
+            "#,
+        )
+        .unwrap();
+        config.apps
+    }
+
+    fn with_profile(profile: &str) -> OpenOptions {
+        OpenOptions { profile: Some(profile.to_string()), ..OpenOptions::default() }
+    }
+
+    #[test]
+    fn prefers_the_exact_app_name_over_other_cases() {
+        let apps = apps();
+        assert_eq!(find(&apps, "Code", "work").unwrap().args, ["--profile", "work"]);
+        assert_eq!(find(&apps, "code", "work").unwrap().args, ["--lowercase"]);
+        assert_eq!(find(&apps, "SLACK", "quiet").unwrap().args, ["--quiet"]);
+        assert!(find(&apps, "Slack", "work").is_none());
+    }
+
+    #[test]
+    fn applies_the_default_profile_unless_another_is_named() {
+        let apps = apps();
+        let options = OpenOptions { args: vec!["main.rs".to_string()], ..OpenOptions::default() };
+
+        let applied = apply_from(&apps, "Code", &options).unwrap();
+        assert_eq!(applied.args, ["--new-window", "main.rs"]);
+        assert_eq!(applied.mode, OpenMode::Focus);
+
+        let applied = apply_from(&apps, "Mail", &options).unwrap();
+        assert_eq!(applied.args, ["main.rs"]);
+    }
+
+    #[test]
+    fn applies_a_named_profile() {
+        let apps = apps();
+
+        let applied = apply_from(&apps, "Code", &with_profile("work")).unwrap();
+        assert_eq!(applied.env, [("PROFILE".to_string(), "work".to_string())]);
+        assert_eq!(applied.mode, OpenMode::NewInstance);
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(applied.cwd, Some(PathBuf::from(home).join("work")));
+        }
+
+        let background = OpenOptions { mode: OpenMode::Background, ..with_profile("work") };
+        assert_eq!(apply_from(&apps, "Code", &background).unwrap().mode, OpenMode::Background);
+
+        let err = apply_from(&apps, "Mail", &with_profile("work")).unwrap_err();
+        assert_eq!(err.to_string(), "No launch profile 'work' for Mail");
+    }
+}
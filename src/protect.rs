@@ -0,0 +1,122 @@
+use std::{path::Path, process::Command};
+
+use crate::config::KillConfig;
+
+/// Applications that are only killed with an explicit override.
+#[derive(Default)]
+pub struct ProtectedApps(Vec<String>);
+
+impl ProtectedApps {
+    /// The configured list plus, unless disabled, the terminal qapp runs in
+    /// and any app among qapp's ancestor processes.
+    pub fn load(config: &KillConfig) -> Self {
+        let mut apps = config.protected.clone();
+
+        if config.protect_host {
+            apps.extend(host_terminal());
+            apps.extend(ancestor_apps());
+        }
+
+        apps.sort();
+        apps.dedup();
+        Self(apps)
+    }
+
+    pub fn contains(&self, app_name: &str) -> bool {
+        self.0.iter().any(|app| app.eq_ignore_ascii_case(app_name))
+    }
+}
+
+fn host_terminal() -> Option<String> {
+    let program = std::env::var("TERM_PROGRAM").ok()?;
+
+    let name = match program.as_str() {
+        "Apple_Terminal" => "Terminal",
+        "iTerm.app" => "iTerm2",
+        "WarpTerminal" => "Warp",
+        "vscode" => "Code",
+        "ghostty" => "Ghostty",
+        "WezTerm" => "WezTerm",
+        other => other,
+    };
+
+    Some(name.to_string())
+}
+
+/// Names of the app bundles (or plain executables) of qapp's parent
+/// processes, e.g. `iTerm2` when launched from a shell inside iTerm.
+fn ancestor_apps() -> Vec<String> {
+    let mut apps = Vec::new();
+    let mut pid = std::os::unix::process::parent_id();
+
+    // Stop at launchd/init, and don't walk forever on odd process tables
+    for depth in 0..16 {
+        if pid <= 1 {
+            break;
+        }
+
+        let Some((parent, command)) = process_info(pid) else {
+            break;
+        };
+
+        if let Some(bundle) = app_bundle_name(&command) {
+            apps.push(bundle);
+        } else if depth == 0 && let Some(name) = Path::new(&command).file_name() {
+            apps.push(name.to_string_lossy().into_owned());
+        }
+
+        pid = parent;
+    }
+
+    apps
+}
+
+fn process_info(pid: u32) -> Option<(u32, String)> {
+    let output = Command::new("ps")
+        .args(["-o", "ppid=,comm=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+
+    let output_str = String::from_utf8(output.stdout).ok()?;
+    let (ppid, command) = output_str.trim().split_once(char::is_whitespace)?;
+
+    Some((ppid.trim().parse().ok()?, command.trim().to_string()))
+}
+
+fn app_bundle_name(command: &str) -> Option<String> {
+    let bundle = command.split(".app/").next().filter(|prefix| prefix.len() < command.len())?;
+    Path::new(bundle)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_outermost_app_bundle() {
+        assert_eq!(app_bundle_name("/Applications/iTerm.app/Contents/MacOS/iTerm2").as_deref(), Some("iTerm"));
+        assert_eq!(
+            app_bundle_name("/Applications/Xcode.app/Contents/Developer/Applications/Simulator.app/Contents/MacOS/Simulator")
+                .as_deref(),
+            Some("Xcode")
+        );
+        assert_eq!(app_bundle_name("/bin/zsh"), None);
+        assert_eq!(app_bundle_name("/Applications/Notes.app"), None);
+    }
+
+    #[test]
+    fn matches_configured_apps_ignoring_case() {
+        let config = KillConfig {
+            protected: vec!["Finder".to_string(), "iTerm2".to_string()],
+            protect_host: false,
+            ..KillConfig::default()
+        };
+        let protected = ProtectedApps::load(&config);
+
+        assert!(protected.contains("FINDER"));
+        assert!(protected.contains("iterm2"));
+        assert!(!protected.contains("iTerm"));
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{self, OpenOptions},
+    hooks::{self, HookEvent},
+    open_application_target,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// How long to wait for processes to die after a force quit
+const FORCE_QUIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Progress of a restart, reported as it happens.
+pub enum Phase {
+    Quitting,
+    /// Waiting for the given number of processes to exit
+    Waiting(usize),
+    ForceQuitting,
+    Relaunching,
+}
+
+impl Phase {
+    pub fn describe(&self) -> String {
+        match self {
+            Phase::Quitting => "quitting…".to_string(),
+            Phase::Waiting(count) => format!("waiting for {} process(es) to exit…", count),
+            Phase::ForceQuitting => "not responding, force quitting…".to_string(),
+            Phase::Relaunching => "relaunching…".to_string(),
+        }
+    }
+}
+
+/// Quits an app, force quitting it if it is still alive after
+/// `escalate_after`, waits until all of its processes are gone and then
+/// opens the same bundle again.
+pub fn restart(app_name: &str, escalate_after: Duration, mut on_phase: impl FnMut(Phase)) -> Result<()> {
+    let backend = backend::current();
+
+    let app = backend
+        .running_apps()?
+        .into_iter()
+        .find(|app| app.name == app_name)
+        .context(format!("Application not running: {}", app_name))?;
+
+    // The kill hooks see the whole stop, including any escalation
+    hooks::around(HookEvent::BeforeKill, HookEvent::AfterKill, app_name, || {
+        on_phase(Phase::Quitting);
+        backend.quit(app_name)?;
+
+        if !wait_for_exit(&app, escalate_after, &mut on_phase)? {
+            on_phase(Phase::ForceQuitting);
+            backend.force_quit(app_name)?;
+
+            if !wait_for_exit(&app, FORCE_QUIT_TIMEOUT, &mut on_phase)? {
+                bail!("{} is still running after a force quit", app_name);
+            }
+        }
+        Ok(())
+    })?;
+
+    on_phase(Phase::Relaunching);
+
+    // Relaunch the exact bundle that was running when it still exists
+    let target = app
+        .path
+        .as_deref()
+        .filter(|path| Path::new(path).exists())
+        .unwrap_or(app_name);
+    open_application_target(target, app_name, &OpenOptions::default())
+}
+
+/// Polls until no process of the app is left, returning false on timeout.
+fn wait_for_exit(app: &backend::AppInfo, timeout: Duration, on_phase: &mut impl FnMut(Phase)) -> Result<bool> {
+    let backend = backend::current();
+    // A timeout too long to represent never comes
+    let deadline = Instant::now().checked_add(timeout);
+    let mut last_count = None;
+
+    loop {
+        let count = backend.process_ids(app)?.len();
+        if count == 0 {
+            return Ok(true);
+        }
+
+        if last_count != Some(count) {
+            on_phase(Phase::Waiting(count));
+            last_count = Some(count);
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_running_applications;
+
+    fn restart_phases(app_name: &str, escalate_after: Duration) -> Result<Vec<String>> {
+        let mut phases = Vec::new();
+        restart(app_name, escalate_after, |phase| phases.push(phase.describe()))?;
+        Ok(phases)
+    }
+
+    #[test]
+    fn quits_then_relaunches() {
+        let _sandbox = backend::sandbox();
+
+        let phases = restart_phases("Terminal", Duration::MAX).unwrap();
+        assert_eq!(phases, ["quitting…", "relaunching…"]);
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Terminal"]);
+    }
+
+    #[test]
+    fn force_quits_an_app_that_outlives_the_timeout() {
+        let _sandbox = backend::sandbox();
+        backend::hang("Terminal");
+
+        let phases = restart_phases("Terminal", Duration::ZERO).unwrap();
+        assert_eq!(
+            phases,
+            ["quitting…", "waiting for 1 process(es) to exit…", "not responding, force quitting…", "relaunching…"]
+        );
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Terminal"]);
+    }
+
+    #[test]
+    fn refuses_apps_that_arent_running() {
+        let _sandbox = backend::sandbox();
+
+        let err = restart_phases("Slack", Duration::ZERO).unwrap_err();
+        assert_eq!(err.to_string(), "Application not running: Slack");
+    }
+}
@@ -0,0 +1,458 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{self, Usage},
+    config::Config,
+    daemon::DaemonBackend,
+    duration,
+    force_kill_specific_application,
+    history::KillHistory,
+    kill_specific_application,
+    protect::ProtectedApps,
+    restart, size,
+    theme::{self, Role},
+};
+
+/// How often the daemon evaluates the rules.
+pub const INTERVAL: Duration = Duration::from_secs(10);
+
+// Apps using less CPU than this are idle, unless a rule says otherwise
+const DEFAULT_IDLE_CPU: f64 = 1.0;
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+
+// Gap between the two samples a dry run without the daemon takes
+const CHECK_SAMPLE_GAP: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    Quit,
+    ForceQuit,
+    Restart,
+    Hide,
+    Minimize,
+}
+
+impl RuleAction {
+    pub fn verb(self) -> &'static str {
+        match self {
+            RuleAction::Quit => "quit",
+            RuleAction::ForceQuit => "force quit",
+            RuleAction::Restart => "restart",
+            RuleAction::Hide => "hide",
+            RuleAction::Minimize => "minimize",
+        }
+    }
+}
+
+/// An action taken on apps once all of the rule's conditions hold, from a
+/// `[[rules]]` config entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawRule")]
+pub struct Rule {
+    pub name: String,
+    pub apps: Vec<String>,
+    /// How long an app must stay below `idle_cpu`
+    pub idle: Option<Duration>,
+    /// CPU use in percent under which an app counts as idle
+    pub idle_cpu: f64,
+    /// Resident memory in bytes an app must exceed
+    pub memory_above: Option<u64>,
+    /// How many of `apps` may run at once; the newest ones beyond it match
+    pub max_running: Option<usize>,
+    pub action: RuleAction,
+    /// How long to leave an app alone after acting on it
+    pub cooldown: Duration,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct RawRule {
+    name: Option<String>,
+    app: Option<String>,
+    #[serde(default)]
+    apps: Vec<String>,
+    idle: Option<String>,
+    idle_cpu: Option<f64>,
+    memory_above: Option<String>,
+    max_running: Option<usize>,
+    action: RuleAction,
+    cooldown: Option<String>,
+}
+
+impl TryFrom<RawRule> for Rule {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawRule) -> Result<Self> {
+        let mut apps = raw.apps;
+        apps.extend(raw.app);
+        if apps.is_empty() {
+            bail!("A rule needs `app` or `apps`");
+        }
+        if raw.idle.is_none() && raw.memory_above.is_none() && raw.max_running.is_none() {
+            bail!("A rule needs at least one of `idle`, `memory-above` or `max-running`");
+        }
+
+        let mut rule = Self {
+            name: String::new(),
+            apps,
+            idle: raw.idle.as_deref().map(duration::parse).transpose()?,
+            idle_cpu: raw.idle_cpu.unwrap_or(DEFAULT_IDLE_CPU),
+            memory_above: raw.memory_above.as_deref().map(size::parse).transpose()?,
+            max_running: raw.max_running,
+            action: raw.action,
+            cooldown: raw.cooldown.as_deref().map(duration::parse).transpose()?.unwrap_or(DEFAULT_COOLDOWN),
+        };
+        rule.name = raw.name.unwrap_or_else(|| rule.describe());
+        Ok(rule)
+    }
+}
+
+impl Rule {
+    /// E.g. `quit Zoom when idle for 30m`.
+    fn describe(&self) -> String {
+        let mut conditions = Vec::new();
+        if let Some(idle) = self.idle {
+            conditions.push(format!("idle for {}", duration::format(idle)));
+        }
+        if let Some(limit) = self.memory_above {
+            conditions.push(format!("using more than {}", size::format(limit)));
+        }
+        if let Some(max) = self.max_running {
+            conditions.push(format!("more than {} running", max));
+        }
+
+        format!("{} {} when {}", self.action.verb(), self.apps.join(", "), conditions.join(" and "))
+    }
+
+    fn applies_to(&self, app_name: &str) -> bool {
+        self.apps.iter().any(|app| app.eq_ignore_ascii_case(app_name))
+    }
+}
+
+/// A rule's conditions checked against one running app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    /// Position of the rule in the config
+    pub index: usize,
+    pub rule: String,
+    pub app: String,
+    pub action: RuleAction,
+    /// Whether every condition holds
+    pub triggered: bool,
+    /// What was observed for each condition
+    pub details: Vec<String>,
+    /// Why a triggered rule isn't acted on yet
+    pub held: Option<String>,
+}
+
+impl Verdict {
+    pub fn actionable(&self) -> bool {
+        self.triggered && self.held.is_none()
+    }
+}
+
+/// Evaluates rules against successive samples of the running apps, keeping
+/// the history that idle times and cooldowns need.
+pub struct Engine {
+    rules: Vec<Rule>,
+    protected: ProtectedApps,
+    escalate_after: Duration,
+    /// Last CPU time seen per app, to turn into a rate
+    cpu: HashMap<String, (Instant, Duration)>,
+    /// Since when each app has been idle, per rule
+    idle_since: HashMap<(usize, String), Instant>,
+    acted_at: HashMap<(usize, String), Instant>,
+}
+
+impl Engine {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            rules: config.rules.clone(),
+            protected: ProtectedApps::load(&config.kill),
+            escalate_after: config.kill.escalate_after,
+            cpu: HashMap::new(),
+            idle_since: HashMap::new(),
+            acted_at: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Samples the running apps and checks every rule against them.
+    pub fn evaluate(&mut self) -> Result<Vec<Verdict>> {
+        let backend = backend::current();
+        let now = Instant::now();
+
+        let running: Vec<_> = backend
+            .running_apps()?
+            .into_iter()
+            .filter(|app| self.rules.iter().any(|rule| rule.applies_to(&app.name)))
+            .collect();
+
+        let mut usage: HashMap<String, Usage> = HashMap::new();
+        // CPU use over the interval since the last sample, and its start
+        let mut cpu_percent: HashMap<String, (f64, Instant)> = HashMap::new();
+        for app in &running {
+            // An app that exits while being sampled is left out
+            let Ok(sample) = backend.usage(app) else {
+                continue;
+            };
+
+            if let Some((at, cpu_time)) = self.cpu.get(&app.name) {
+                let elapsed = now.duration_since(*at).as_secs_f64();
+                let used = sample.cpu_time.saturating_sub(*cpu_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    cpu_percent.insert(app.name.clone(), (used / elapsed * 100.0, *at));
+                }
+            }
+            self.cpu.insert(app.name.clone(), (now, sample.cpu_time));
+            usage.insert(app.name.clone(), sample);
+        }
+
+        // Forget apps that have quit, so a relaunch starts afresh
+        self.cpu.retain(|name, _| usage.contains_key(name));
+        self.idle_since.retain(|(_, name), _| usage.contains_key(name));
+
+        let mut verdicts = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let mut matched: Vec<(&String, &Usage)> = running
+                .iter()
+                .filter(|app| rule.applies_to(&app.name))
+                .filter_map(|app| usage.get_key_value(&app.name))
+                .collect();
+
+            // Newest first, so the apps beyond the limit are the last launched
+            matched.sort_by_key(|(_, usage)| usage.uptime);
+            let over_limit = rule.max_running.map(|max| matched.len().saturating_sub(max)).unwrap_or(0);
+
+            for (position, (name, usage)) in matched.iter().enumerate() {
+                let key = (index, name.to_string());
+                let mut triggered = true;
+                let mut details = Vec::new();
+
+                if let Some(idle) = rule.idle {
+                    match cpu_percent.get(*name) {
+                        None => {
+                            details.push("measuring CPU use".to_string());
+                            triggered = false;
+                        },
+                        Some(&(percent, interval_start)) if percent < rule.idle_cpu => {
+                            // Idle through the whole interval the rate covers
+                            let since = *self.idle_since.entry(key.clone()).or_insert(interval_start);
+                            let idle_for = now.duration_since(since);
+                            details.push(format!("idle for {} of {}", duration::format(idle_for), duration::format(idle)));
+                            triggered &= idle_for >= idle;
+                        },
+                        Some(&(percent, _)) => {
+                            self.idle_since.remove(&key);
+                            details.push(format!("busy at {:.1}% CPU", percent));
+                            triggered = false;
+                        },
+                    }
+                }
+
+                if let Some(limit) = rule.memory_above {
+                    details.push(format!("using {} of {}", size::format(usage.memory), size::format(limit)));
+                    triggered &= usage.memory > limit;
+                }
+
+                if let Some(max) = rule.max_running {
+                    let beyond = position < over_limit;
+                    let note = if beyond { ", one of the newest" } else { "" };
+                    details.push(format!("{} of at most {} running{}", matched.len(), max, note));
+                    triggered &= beyond;
+                }
+
+                let held = if !triggered {
+                    None
+                } else if self.protected.contains(name) {
+                    Some("protected".to_string())
+                } else {
+                    self.acted_at
+                        .get(&key)
+                        .map(|at| rule.cooldown.saturating_sub(now.duration_since(*at)))
+                        .filter(|left| !left.is_zero())
+                        .map(|left| format!("cooling down for {}", duration::format(left)))
+                };
+
+                verdicts.push(Verdict {
+                    index,
+                    rule: rule.name.clone(),
+                    app: name.to_string(),
+                    action: rule.action,
+                    triggered,
+                    details,
+                    held,
+                });
+            }
+        }
+
+        Ok(verdicts)
+    }
+
+    /// Starts the cooldown for an app the verdict's rule is about to act on.
+    pub fn mark_acted(&mut self, verdict: &Verdict) {
+        self.acted_at.insert((verdict.index, verdict.app.clone()), Instant::now());
+    }
+
+    pub fn escalate_after(&self) -> Duration {
+        self.escalate_after
+    }
+}
+
+/// Carries out a triggered verdict through the same paths as the commands.
+pub fn execute(verdict: &Verdict, escalate_after: Duration) -> Result<()> {
+    let app = &verdict.app;
+    match verdict.action {
+        RuleAction::Quit => {
+            kill_specific_application(app)?;
+            KillHistory::record(std::slice::from_ref(app))
+        },
+        RuleAction::ForceQuit => {
+            force_kill_specific_application(app)?;
+            KillHistory::record(std::slice::from_ref(app))
+        },
+        RuleAction::Restart => restart::restart(app, escalate_after, |_| {}),
+        RuleAction::Hide => backend::current().hide(app),
+        RuleAction::Minimize => backend::current().minimize(app),
+    }
+}
+
+/// Prints what every rule would do right now without doing it. A running
+/// daemon is asked, since only it knows how long apps have been idle.
+pub fn check(config: &Config) -> Result<()> {
+    let theme = theme::current();
+
+    if config.rules.is_empty() {
+        println!("{}", theme.paint("No rules configured.", Role::Warning));
+        return Ok(());
+    }
+
+    let verdicts = match DaemonBackend::connect() {
+        Some(daemon) => daemon.check_rules()?,
+        None => {
+            // CPU use needs two samples to measure
+            let mut engine = Engine::new(config);
+            engine.evaluate()?;
+            thread::sleep(CHECK_SAMPLE_GAP);
+            let verdicts = engine.evaluate()?;
+
+            if config.rules.iter().any(|rule| rule.idle.is_some()) {
+                println!(
+                    "{}",
+                    theme.paint("The daemon isn't running, so idle times only cover the last second.", Role::Warning)
+                );
+            }
+            verdicts
+        },
+    };
+
+    for (index, rule) in config.rules.iter().enumerate() {
+        println!("{}", theme.paint(&rule.name, Role::Accent));
+
+        let mut any = false;
+        for verdict in verdicts.iter().filter(|verdict| verdict.index == index) {
+            any = true;
+            let outcome = match (&verdict.held, verdict.triggered) {
+                (_, false) => theme.paint("no action", Role::Hint),
+                (Some(reason), true) => theme.paint(&format!("would {}, but {}", rule.action.verb(), reason), Role::Warning),
+                (None, true) => theme.paint(&format!("would {}", rule.action.verb()), Role::Danger),
+            };
+            println!(
+                "  {}: {} ({})",
+                theme.paint(&verdict.app, Role::Name),
+                outcome,
+                verdict.details.join(", ")
+            );
+        }
+
+        if !any {
+            println!("  {}", theme.paint("none of its apps are running", Role::Hint));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(toml: &str) -> Result<Rule> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    #[test]
+    fn names_rules_after_what_they_do() {
+        let rule = rule(
+            r#"
+            app = "Zoom"
+            idle = "30m"
+            memory-above = "2GB"
+            action = "quit"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rule.name, "quit Zoom when idle for 30m and using more than 2.0 GB");
+        assert_eq!(rule.idle_cpu, DEFAULT_IDLE_CPU);
+        assert_eq!(rule.cooldown, DEFAULT_COOLDOWN);
+    }
+
+    #[test]
+    fn combines_app_and_apps() {
+        let rule = rule(
+            r#"
+            name = "browsers"
+            apps = ["Safari", "Firefox"]
+            app = "Chrome"
+            max-running = 1
+            action = "force-quit"
+            cooldown = "1m"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rule.name, "browsers");
+        assert_eq!(rule.apps, ["Safari", "Firefox", "Chrome"]);
+        assert_eq!(rule.cooldown, Duration::from_secs(60));
+        assert!(rule.applies_to("firefox"));
+    }
+
+    #[test]
+    fn rejects_incomplete_rules() {
+        let err = rule(
+            r#"
+            idle = "5m"
+            action = "quit"
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("needs `app` or `apps`"), "{}", err);
+
+        let err = rule(
+            r#"
+            app = "Zoom"
+            action = "quit"
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("at least one of"), "{}", err);
+
+        let bad_idle = r#"
+            app = "Zoom"
+            idle = "soon"
+            action = "quit"
+            "#;
+        assert!(rule(bad_idle).is_err());
+    }
+}
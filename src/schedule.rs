@@ -0,0 +1,540 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{
+    backend::{self, OpenOptions},
+    clock::{self, Local, WEEKDAYS},
+    config::Config,
+    daemon::DaemonBackend,
+    duration,
+    history::{time_ago, KillHistory},
+    kill_specific_application, open_specific_application,
+    protect::ProtectedApps,
+    restart, store,
+    theme::{self, Role},
+    workspace,
+};
+
+const SCHEDULE_FILE: &str = "schedule.json";
+
+// Actions found overdue by more than this, e.g. because the daemon wasn't
+// running, are skipped rather than run late
+const GRACE: u64 = 5 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+/// What a schedule does when it comes due.
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ScheduledCommand {
+    /// Open an application
+    #[command(after_long_help = "Examples:
+  qapp at 9:00 open Mail")]
+    Open {
+        name: String,
+    },
+    /// Quit an application
+    #[command(after_long_help = "Examples:
+  qapp at 18:00 kill Slack")]
+    Kill {
+        name: String,
+        /// Allow killing an app on the protected list
+        #[arg(long)]
+        #[serde(default)]
+        allow_protected: bool,
+    },
+    /// Quit an application and open it again
+    #[command(after_long_help = "Examples:
+  qapp at 3:00 restart Docker")]
+    Restart {
+        name: String,
+    },
+    /// Hide an application
+    #[command(after_long_help = "Examples:
+  qapp at 30m hide Slack")]
+    Hide {
+        name: String,
+    },
+    /// Minimize all windows of an application
+    #[command(after_long_help = "Examples:
+  qapp at 30m minimize Safari")]
+    Minimize {
+        name: String,
+    },
+    /// Bring a workspace up or down
+    #[command(after_long_help = "Examples:
+  qapp at 9:00 workspace up coding
+  qapp at 17:30 workspace down coding")]
+    Workspace {
+        direction: Direction,
+        name: String,
+    },
+}
+
+impl ScheduledCommand {
+    /// E.g. `kill Slack` or `workspace up coding`.
+    pub fn describe(&self) -> String {
+        match self {
+            ScheduledCommand::Open { name } => format!("open {}", name),
+            ScheduledCommand::Kill { name, .. } => format!("kill {}", name),
+            ScheduledCommand::Restart { name } => format!("restart {}", name),
+            ScheduledCommand::Hide { name } => format!("hide {}", name),
+            ScheduledCommand::Minimize { name } => format!("minimize {}", name),
+            ScheduledCommand::Workspace { direction: Direction::Up, name } => format!("workspace up {}", name),
+            ScheduledCommand::Workspace { direction: Direction::Down, name } => format!("workspace down {}", name),
+        }
+    }
+
+    /// Runs the command the way the matching qapp command would, without
+    /// asking for confirmation since scheduling it was the confirmation.
+    fn execute(&self, config: &Config) -> Result<()> {
+        match self {
+            ScheduledCommand::Open { name } => open_specific_application(name, &OpenOptions::default()),
+            ScheduledCommand::Kill { name, allow_protected } => {
+                if !allow_protected && ProtectedApps::load(&config.kill).contains(name) {
+                    bail!("{} is protected", name);
+                }
+                kill_specific_application(name)?;
+                KillHistory::record(std::slice::from_ref(name))
+            },
+            ScheduledCommand::Restart { name } => restart::restart(name, config.kill.escalate_after, |_| {}),
+            ScheduledCommand::Hide { name } => backend::current().hide(name),
+            ScheduledCommand::Minimize { name } => backend::current().minimize(name),
+            ScheduledCommand::Workspace { direction, name } => {
+                let workspace = workspace::named(config, name)?;
+                let summary = match direction {
+                    Direction::Up => workspace.up(config)?,
+                    Direction::Down => workspace.down(config)?,
+                };
+                summary.print();
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Parses a command line such as `kill Slack` into a scheduled command.
+#[derive(Parser)]
+#[command(name = "run", no_binary_name = true)]
+struct CommandLine {
+    #[command(subcommand)]
+    command: ScheduledCommand,
+}
+
+/// A one-off action from `qapp at` or `--in`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Job {
+    id: u32,
+    /// Unix timestamp to run at
+    at: u64,
+    #[serde(flatten)]
+    command: ScheduledCommand,
+}
+
+/// Persisted one-off jobs plus when each recurring schedule last ran.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScheduleState {
+    next_id: u32,
+    jobs: Vec<Job>,
+    /// Keyed by the schedule's description, so edits to the config start
+    /// afresh
+    #[serde(default)]
+    last_runs: BTreeMap<String, u64>,
+}
+
+impl ScheduleState {
+    fn load() -> Result<Self> {
+        store::load(SCHEDULE_FILE)
+    }
+
+    /// Changes the state under a lock, since the daemon updates it while
+    /// other invocations add and cancel jobs.
+    fn update<R>(change: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+        store::update(SCHEDULE_FILE, change)
+    }
+}
+
+/// A recurring action from a `[[schedules]]` config entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawSchedule")]
+pub struct Schedule {
+    /// Minutes past midnight, local time
+    at: u32,
+    /// Indexed from Monday
+    days: [bool; 7],
+    command: ScheduledCommand,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSchedule {
+    at: String,
+    #[serde(default)]
+    days: Option<Days>,
+    run: CommandWords,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Days {
+    Named(String),
+    List(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CommandWords {
+    Line(String),
+    Words(Vec<String>),
+}
+
+impl TryFrom<RawSchedule> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawSchedule) -> Result<Self> {
+        let days = match raw.days {
+            None => [true; 7],
+            Some(Days::Named(name)) => match name.as_str() {
+                "daily" => [true; 7],
+                "weekdays" => [true, true, true, true, true, false, false],
+                "weekends" => [false, false, false, false, false, true, true],
+                day => day_set(&[day.to_string()])?,
+            },
+            Some(Days::List(list)) => day_set(&list)?,
+        };
+
+        // Use the list form for names with spaces
+        let words = match raw.run {
+            CommandWords::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+            CommandWords::Words(words) => words,
+        };
+        let command = CommandLine::try_parse_from(&words)
+            .map_err(|err| anyhow::anyhow!("Invalid command '{}': {}", words.join(" "), err.kind()))?
+            .command;
+
+        Ok(Self { at: clock::parse_time_of_day(&raw.at)?, days, command })
+    }
+}
+
+// In `WEEKDAYS` order. A day can be written as any part of its name at
+// least as long as the abbreviation, e.g. "tue", "tues" or "tuesday"
+const DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+fn day_set(names: &[String]) -> Result<[bool; 7]> {
+    let mut days = [false; 7];
+    for name in names {
+        let prefix = name.to_lowercase();
+        let Some(index) = DAY_NAMES.iter().position(|day| prefix.len() >= 3 && day.starts_with(&prefix)) else {
+            bail!("Unknown day '{}'; use mon..sun, weekdays, weekends or daily", name);
+        };
+        days[index] = true;
+    }
+    Ok(days)
+}
+
+impl Schedule {
+    fn describe_days(&self) -> String {
+        match self.days {
+            [true, true, true, true, true, true, true] => "daily".to_string(),
+            [true, true, true, true, true, false, false] => "weekdays".to_string(),
+            [false, false, false, false, false, true, true] => "weekends".to_string(),
+            days => WEEKDAYS
+                .iter()
+                .zip(days)
+                .filter(|(_, on)| *on)
+                .map(|(day, _)| *day)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+
+    fn key(&self) -> String {
+        format!("{} {} {}", self.describe_days(), clock::format_time_of_day(self.at), self.command.describe())
+    }
+
+    /// The latest time this schedule was due at or before `now`.
+    fn last_due(&self, now: Local) -> Option<u64> {
+        (0..8)
+            .map(|days_ago| now.at(-days_ago, self.at))
+            .find(|at| *at <= now.timestamp() && self.days[now.with_timestamp(*at).weekday()])
+    }
+
+    /// The next time this schedule is due after `now`.
+    fn next_due(&self, now: Local) -> Option<u64> {
+        (0..8)
+            .map(|days| now.at(days, self.at))
+            .find(|at| *at > now.timestamp() && self.days[now.with_timestamp(*at).weekday()])
+    }
+}
+
+/// Parses `18:00` as the next time the clock shows it, or a duration such
+/// as `45m` as that long from now.
+pub fn parse_when(input: &str) -> Result<u64> {
+    let now = store::now();
+
+    if input.contains(':') {
+        let minutes = clock::parse_time_of_day(input)?;
+        let local = Local::new(now, clock::local_offset());
+        let today = local.at(0, minutes);
+        return Ok(if today > now { today } else { local.at(1, minutes) });
+    }
+
+    let after = duration::parse(input).context(format!("Invalid time '{}', expected HH:MM or a duration", input))?;
+    Ok(now.saturating_add(after.as_secs()))
+}
+
+/// Stores a one-off job to run at a Unix timestamp.
+pub fn add(at: u64, command: ScheduledCommand) -> Result<()> {
+    let theme = theme::current();
+    let id = ScheduleState::update(|state| {
+        state.next_id += 1;
+        state.jobs.push(Job { id: state.next_id, at, command: command.clone() });
+        Ok(state.next_id)
+    })?;
+
+    let local = Local::new(at, clock::local_offset());
+    println!(
+        "{} {} at {} (#{})",
+        theme.paint("Scheduled:", Role::Success),
+        command.describe(),
+        local.describe(),
+        id
+    );
+    if DaemonBackend::connect().is_none() {
+        println!(
+            "{}",
+            theme.paint("The daemon isn't running; scheduled actions only run while it is.", Role::Warning)
+        );
+    }
+    Ok(())
+}
+
+/// Shorthand for `add` with a delay, as in `qapp kill Zoom --in 45m`.
+pub fn add_in(after: Duration, command: ScheduledCommand) -> Result<()> {
+    add(store::now().saturating_add(after.as_secs()), command)
+}
+
+/// Prints the pending one-off jobs and the configured recurring schedules.
+pub fn list(config: &Config) -> Result<()> {
+    let theme = theme::current();
+    let state = ScheduleState::load()?;
+    let offset = clock::local_offset();
+    let now = Local::new(store::now(), offset);
+
+    if state.jobs.is_empty() && config.schedules.is_empty() {
+        println!("{}", theme.paint("Nothing scheduled.", Role::Warning));
+        return Ok(());
+    }
+
+    let mut jobs = state.jobs;
+    jobs.sort_by_key(|job| job.at);
+    for job in &jobs {
+        println!(
+            "{} {}  {}",
+            theme.paint(&format!("#{}", job.id), Role::Accent),
+            Local::new(job.at, offset).describe(),
+            job.command.describe()
+        );
+    }
+
+    for schedule in &config.schedules {
+        let next = schedule
+            .next_due(now)
+            .map(|at| format!("next {}", Local::new(at, offset).describe()))
+            .unwrap_or_default();
+        println!(
+            "{} {}  {}  ({})",
+            theme.paint(&schedule.describe_days(), Role::Accent),
+            clock::format_time_of_day(schedule.at),
+            schedule.command.describe(),
+            next
+        );
+    }
+    Ok(())
+}
+
+/// IDs of the pending one-off jobs.
+pub fn ids() -> Result<Vec<String>> {
+    Ok(ScheduleState::load()?.jobs.iter().map(|job| job.id.to_string()).collect())
+}
+
+/// Cancels a one-off job.
+pub fn remove(id: u32) -> Result<()> {
+    let theme = theme::current();
+    let job = ScheduleState::update(|state| {
+        let Some(position) = state.jobs.iter().position(|job| job.id == id) else {
+            bail!("No scheduled action #{}", id);
+        };
+        Ok(state.jobs.remove(position))
+    })?;
+
+    println!("{} {} (#{})", theme.paint("Cancelled:", Role::Success), job.command.describe(), id);
+    Ok(())
+}
+
+/// Runs the jobs and schedules that have come due, for the daemon.
+pub fn run_due(config: &Config) -> Result<()> {
+    let theme = theme::current();
+    let state = ScheduleState::load()?;
+    if state.jobs.is_empty() && config.schedules.is_empty() {
+        return Ok(());
+    }
+
+    // Saved before running anything, so a slow or failing action can't run twice
+    let due = ScheduleState::update(|state| {
+        let now = store::now();
+        let mut due = Vec::new();
+
+        let (ready, pending): (Vec<Job>, Vec<Job>) = state.jobs.drain(..).partition(|job| job.at <= now);
+        state.jobs = pending;
+        for job in ready {
+            if now - job.at > GRACE {
+                println!("{} {} (due {})", theme.paint("Missed:", Role::Warning), job.command.describe(), time_ago(job.at));
+            } else {
+                due.push(job.command);
+            }
+        }
+
+        let local = Local::new(now, clock::local_offset());
+        for schedule in &config.schedules {
+            let Some(at) = schedule.last_due(local) else {
+                continue;
+            };
+            let key = schedule.key();
+            if now - at <= GRACE && state.last_runs.get(&key).is_none_or(|last| *last < at) {
+                state.last_runs.insert(key, now);
+                due.push(schedule.command.clone());
+            }
+        }
+        Ok(due)
+    })?;
+
+    for command in due {
+        println!("{} {}", theme.paint("Running:", Role::Accent), command.describe());
+        if let Err(err) = command.execute(config) {
+            eprintln!("{} {:#}", theme.paint("Failed:", Role::Danger), err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+
+    // Tuesday 2024-01-02 18:00 UTC
+    const TUESDAY_EVENING: u64 = 1_704_218_400;
+
+    fn schedule(toml: &str) -> Result<Schedule> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    #[test]
+    fn parses_days_and_commands() {
+        let coding = schedule(
+            r#"
+            at = "9:00"
+            days = ["Monday", "wed"]
+            run = ["workspace", "up", "coding"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(coding.key(), "mon,wed 09:00 workspace up coding");
+
+        let err = schedule(
+            r#"
+            at = "9:00"
+            days = "someday"
+            run = "open Mail"
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Unknown day 'someday'"), "{}", err);
+        let unknown = r#"
+            at = "9:00"
+            run = "launch Mail"
+            "#;
+        assert!(schedule(unknown).is_err());
+    }
+
+    #[test]
+    fn accepts_days_only_by_their_own_name() {
+        let on = |day: &str| schedule(&format!("at = \"9:00\"\ndays = \"{}\"\nrun = \"open Mail\"", day));
+
+        assert_eq!(on("Tues").unwrap().key(), "tue 09:00 open Mail");
+        assert_eq!(on("THURSDAY").unwrap().key(), "thu 09:00 open Mail");
+        for day in ["monkey", "mondays", "th", "sundae"] {
+            let err = on(day).unwrap_err().to_string();
+            assert!(err.contains(&format!("Unknown day '{}'", day)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn finds_due_times_on_matching_days() {
+        let now = Local::new(TUESDAY_EVENING, 0);
+
+        let weekdays = schedule(
+            r#"
+            at = "9:00"
+            days = "weekdays"
+            run = "open Mail"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(weekdays.last_due(now), Some(TUESDAY_EVENING - 9 * HOUR));
+        assert_eq!(weekdays.next_due(now), Some(TUESDAY_EVENING + 15 * HOUR));
+
+        let weekends = schedule(
+            r#"
+            at = "9:00"
+            days = "weekends"
+            run = "open Mail"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(weekends.last_due(now), Some(TUESDAY_EVENING - 2 * DAY - 9 * HOUR));
+        assert_eq!(weekends.next_due(now), Some(TUESDAY_EVENING + 3 * DAY + 15 * HOUR));
+    }
+
+    #[test]
+    fn a_schedule_due_right_now_is_next_due_tomorrow() {
+        let now = Local::new(TUESDAY_EVENING, 0);
+        let daily = schedule(
+            r#"
+            at = "18:00"
+            run = "kill Slack"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(daily.last_due(now), Some(TUESDAY_EVENING));
+        assert_eq!(daily.next_due(now), Some(TUESDAY_EVENING + DAY));
+    }
+
+    #[test]
+    fn uses_the_local_day() {
+        // Already Wednesday 01:00 at UTC+07:00, so a Tuesday-only schedule
+        // last ran the evening before and next runs in six days
+        let now = Local::new(TUESDAY_EVENING, 7 * HOUR as i64);
+        let tuesdays = schedule(
+            r#"
+            at = "20:00"
+            days = "tue"
+            run = "open Mail"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(tuesdays.last_due(now), Some(TUESDAY_EVENING - 5 * HOUR));
+        assert_eq!(tuesdays.next_due(now), Some(TUESDAY_EVENING - 5 * HOUR + 7 * DAY));
+    }
+}
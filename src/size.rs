@@ -0,0 +1,74 @@
+use anyhow::{bail, Context, Result};
+
+const UNITS: &[(&str, u64)] = &[
+    ("b", 1),
+    ("k", 1 << 10),
+    ("kb", 1 << 10),
+    ("m", 1 << 20),
+    ("mb", 1 << 20),
+    ("g", 1 << 30),
+    ("gb", 1 << 30),
+    ("t", 1 << 40),
+    ("tb", 1 << 40),
+];
+
+/// Parses sizes such as `512MB`, `4GB` or `1.5G` into bytes, counting in
+/// powers of 1024. A bare number is taken as bytes.
+pub fn parse(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let number: f64 = number.parse().context(format!("Invalid size '{}'", input))?;
+    let unit = unit.trim().to_lowercase();
+    let Some((_, multiplier)) = UNITS.iter().find(|(name, _)| unit.is_empty() || *name == unit) else {
+        bail!("Unknown unit '{}' in size '{}'", unit, input);
+    };
+
+    Ok((number * *multiplier as f64) as u64)
+}
+
+/// Formats a size with one decimal in the largest fitting unit, e.g. `4.2 GB`.
+pub fn format(bytes: u64) -> String {
+    let (unit, multiplier) = [("TB", 1u64 << 40), ("GB", 1 << 30), ("MB", 1 << 20), ("KB", 1 << 10)]
+        .into_iter()
+        .find(|(_, multiplier)| bytes >= *multiplier)
+        .unwrap_or(("B", 1));
+
+    if multiplier == 1 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", bytes as f64 / multiplier as f64, unit)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes_in_powers_of_1024() {
+        assert_eq!(parse("512").unwrap(), 512);
+        assert_eq!(parse("4k").unwrap(), 4 << 10);
+        assert_eq!(parse("512MB").unwrap(), 512 << 20);
+        assert_eq!(parse("1.5G").unwrap(), 3 << 29);
+        assert_eq!(parse("2 tb").unwrap(), 2 << 40);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse("").is_err());
+        assert!(parse("GB").is_err());
+        assert!(parse("4 pb").unwrap_err().to_string().contains("Unknown unit 'pb'"));
+    }
+
+    #[test]
+    fn formats_in_the_largest_unit() {
+        assert_eq!(format(900), "900 B");
+        assert_eq!(format(1536), "1.5 KB");
+        assert_eq!(format(4 << 30), "4.0 GB");
+    }
+}
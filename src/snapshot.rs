@@ -0,0 +1,210 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{
+    backend::{AppInfo, OpenOptions},
+    get_running_application_details, history,
+    open_application_target,
+    store,
+    theme::{self, Role},
+};
+
+const SNAPSHOTS_FILE: &str = "snapshots.json";
+
+/// The visible apps that were running at some point in time.
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    saved_at: u64,
+    apps: Vec<AppInfo>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshots {
+    snapshots: BTreeMap<String, Snapshot>,
+}
+
+impl Snapshots {
+    fn load() -> Result<Self> {
+        store::load(SNAPSHOTS_FILE)
+    }
+
+    fn save(&self) -> Result<()> {
+        store::save(SNAPSHOTS_FILE, self)
+    }
+
+    fn get(&self, name: &str) -> Result<&Snapshot> {
+        self.snapshots
+            .get(name)
+            .context(format!("Unknown snapshot: {}", name))
+    }
+}
+
+fn is_running(running: &[AppInfo], app: &AppInfo) -> bool {
+    running.iter().any(|other| match (&app.bundle_id, &other.bundle_id) {
+        (Some(id), Some(other_id)) => id == other_id,
+        _ => app.name == other.name,
+    })
+}
+
+/// Records the currently running apps under `name`, replacing any earlier snapshot.
+pub fn save(name: &str) -> Result<()> {
+    let theme = theme::current();
+    let apps = get_running_application_details()?;
+
+    if apps.is_empty() {
+        bail!("No running applications to snapshot");
+    }
+
+    let count = apps.len();
+    let mut snapshots = Snapshots::load()?;
+    snapshots.snapshots.insert(name.to_string(), Snapshot { saved_at: store::now(), apps });
+    snapshots.save()?;
+
+    println!(
+        "{} {} ({} applications)",
+        theme.paint("Saved snapshot:", Role::Success),
+        theme.paint(name, Role::Name),
+        count,
+    );
+    Ok(())
+}
+
+/// Relaunches the apps from a snapshot that aren't running now.
+pub fn restore(name: &str) -> Result<()> {
+    let theme = theme::current();
+    let snapshots = Snapshots::load()?;
+    let snapshot = snapshots.get(name)?;
+    let running = get_running_application_details()?;
+
+    let mut relaunched = 0;
+    for app in snapshot.apps.iter().filter(|app| !is_running(&running, app)) {
+        // Prefer the exact bundle that was running when it still exists
+        let target = app
+            .path
+            .as_deref()
+            .filter(|path| Path::new(path).exists())
+            .unwrap_or(&app.name);
+
+        match open_application_target(target, &app.name, &OpenOptions::default()) {
+            Ok(()) => {
+                println!("{} {}", theme.paint("Relaunching:", Role::Success), theme.paint(&app.name, Role::Name));
+                relaunched += 1;
+            },
+            Err(err) => {
+                println!("{} {} ({})", theme.paint("Failed:", Role::Danger), theme.paint(&app.name, Role::Name), err);
+            },
+        }
+    }
+
+    if relaunched == 0 {
+        println!("{}", theme.paint("Every application in the snapshot is already running.", Role::Warning));
+    }
+    Ok(())
+}
+
+/// Shows which apps were started or quit since the snapshot was taken.
+pub fn diff(name: &str) -> Result<()> {
+    let theme = theme::current();
+    let snapshots = Snapshots::load()?;
+    let snapshot = snapshots.get(name)?;
+    let running = get_running_application_details()?;
+
+    println!(
+        "{} {} (saved {})",
+        theme.paint("Compared with snapshot:", Role::Accent),
+        theme.paint(name, Role::Name),
+        history::time_ago(snapshot.saved_at),
+    );
+
+    let added: Vec<&AppInfo> = running.iter().filter(|app| !is_running(&snapshot.apps, app)).collect();
+    let removed: Vec<&AppInfo> = snapshot.apps.iter().filter(|app| !is_running(&running, app)).collect();
+
+    for app in &added {
+        println!("{} {}", theme.paint("+", Role::Success), app.name);
+    }
+    for app in &removed {
+        println!("{} {}", theme.paint("-", Role::Danger), app.name);
+    }
+
+    if added.is_empty() && removed.is_empty() {
+        println!("{}", theme.paint("No changes.", Role::Warning));
+    }
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    let theme = theme::current();
+    let snapshots = Snapshots::load()?;
+
+    if snapshots.snapshots.is_empty() {
+        println!("{}", theme.paint("No snapshots saved.", Role::Warning));
+    }
+
+    for (name, snapshot) in &snapshots.snapshots {
+        println!(
+            "{}: {} applications, saved {}",
+            theme.paint(name, Role::Name),
+            snapshot.apps.len(),
+            history::time_ago(snapshot.saved_at),
+        );
+    }
+    Ok(())
+}
+
+pub fn names() -> Result<Vec<String>> {
+    Ok(Snapshots::load()?.snapshots.into_keys().collect())
+}
+
+pub fn remove(name: &str) -> Result<()> {
+    let theme = theme::current();
+    let mut snapshots = Snapshots::load()?;
+
+    if snapshots.snapshots.remove(name).is_none() {
+        bail!("Unknown snapshot: {}", name);
+    }
+
+    snapshots.save()?;
+    println!("{} {}", theme.paint("Removed snapshot:", Role::Success), theme.paint(name, Role::Name));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend, get_running_applications};
+
+    fn app(name: &str, bundle_id: Option<&str>) -> AppInfo {
+        AppInfo { name: name.to_string(), bundle_id: bundle_id.map(str::to_string), path: None }
+    }
+
+    #[test]
+    fn matches_by_bundle_id_when_both_have_one() {
+        let running = [app("Code", Some("com.microsoft.VSCode")), app("Notes", None)];
+
+        assert!(is_running(&running, &app("Visual Studio Code", Some("com.microsoft.VSCode"))));
+        assert!(!is_running(&running, &app("Code", Some("com.example.code"))));
+        assert!(is_running(&running, &app("Code", None)));
+        assert!(is_running(&running, &app("Notes", Some("com.apple.Notes"))));
+        assert!(!is_running(&running, &app("notes", None)));
+    }
+
+    #[test]
+    fn restores_only_what_has_quit() {
+        let _sandbox = backend::sandbox();
+        let fake = backend::current();
+        fake.open("Slack", &OpenOptions::default()).unwrap();
+        save("work").unwrap();
+
+        fake.quit("Slack").unwrap();
+        fake.quit("Terminal").unwrap();
+        fake.open("Mail", &OpenOptions::default()).unwrap();
+        restore("work").unwrap();
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Mail", "Terminal", "Slack"]);
+
+        assert_eq!(names().unwrap(), ["work"]);
+        remove("work").unwrap();
+        assert!(restore("work").is_err());
+        assert!(remove("work").is_err());
+    }
+}
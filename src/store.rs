@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+/// Directory for qapp's persisted state: `$QAPP_DATA_DIR`, or
+/// `~/.local/share/qapp` (honoring `$XDG_DATA_HOME`).
+pub fn data_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("QAPP_DATA_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").context("HOME is not set")?)
+            .join(".local")
+            .join("share"),
+    };
+
+    Ok(base.join("qapp"))
+}
+
+/// Reads a JSON file from the data directory, or the default if it doesn't exist yet.
+pub fn load<T: DeserializeOwned + Default>(file_name: &str) -> Result<T> {
+    let path = data_dir()?.join(file_name);
+
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .context(format!("Failed to read {}", path.display()))?;
+
+    serde_json::from_str(&contents)
+        .context(format!("Failed to parse {}", path.display()))
+}
+
+/// Writes a JSON file to the data directory, replacing it atomically.
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> Result<()> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)
+        .context(format!("Failed to create {}", dir.display()))?;
+
+    let path = dir.join(file_name);
+    let tmp_path = dir.join(format!("{}.tmp", file_name));
+
+    let contents = serde_json::to_string_pretty(value)
+        .context(format!("Failed to serialize {}", file_name))?;
+
+    fs::write(&tmp_path, contents)
+        .context(format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &path)
+        .context(format!("Failed to write {}", path.display()))
+}
+
+/// Loads, changes and saves a JSON file while holding a lock on it, so
+/// separate qapp processes updating the same file can't lose each other's
+/// changes. Only callers that go through here are kept out.
+pub fn update<T, R>(file_name: &str, change: impl FnOnce(&mut T) -> Result<R>) -> Result<R>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)
+        .context(format!("Failed to create {}", dir.display()))?;
+
+    // The file itself is replaced on save, so the lock lives beside it
+    let lock_path = dir.join(format!("{}.lock", file_name));
+    let lock = File::create(&lock_path)
+        .context(format!("Failed to open {}", lock_path.display()))?;
+    lock.lock()
+        .context(format!("Failed to lock {}", lock_path.display()))?;
+
+    let mut value = load(file_name)?;
+    let result = change(&mut value)?;
+    save(file_name, &value)?;
+    Ok(result)
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,323 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::{io::IsTerminal, sync::OnceLock};
+
+use crate::config::Config;
+
+static CURRENT: OnceLock<Theme> = OnceLock::new();
+
+/// When to emit colors, as selected with `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color unless NO_COLOR is set or stdout is not a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The parts of the UI and CLI output that can be colored.
+#[derive(Debug, Clone, Copy)]
+pub enum Role {
+    /// Headers and titles
+    Accent,
+    /// The search query being typed
+    Query,
+    /// Unselected list entries
+    Text,
+    /// The selected list entry
+    Selected,
+    /// Key binding hints in the footer
+    Hint,
+    Success,
+    Danger,
+    Warning,
+    /// Application names in CLI messages
+    Name,
+}
+
+/// Colors for every [`Role`]; `None` leaves the terminal default.
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    accent: Option<Color>,
+    query: Option<Color>,
+    text: Option<Color>,
+    selected: Option<Color>,
+    highlight_fg: Option<Color>,
+    highlight_bg: Option<Color>,
+    hint: Option<Color>,
+    success: Option<Color>,
+    danger: Option<Color>,
+    warning: Option<Color>,
+    name: Option<Color>,
+}
+
+/// A theme defined in the `[themes.<name>]` config table. Unset colors are
+/// taken from `base`, which defaults to the built-in `default` theme.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    pub accent: Option<String>,
+    pub query: Option<String>,
+    pub text: Option<String>,
+    pub selected: Option<String>,
+    pub highlight_fg: Option<String>,
+    pub highlight_bg: Option<String>,
+    pub hint: Option<String>,
+    pub success: Option<String>,
+    pub danger: Option<String>,
+    pub warning: Option<String>,
+    pub name: Option<String>,
+}
+
+impl Theme {
+    fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self {
+                accent: Some(Color::Green),
+                query: Some(Color::Yellow),
+                text: Some(Color::White),
+                selected: Some(Color::Yellow),
+                highlight_fg: Some(Color::White),
+                highlight_bg: Some(Color::Blue),
+                hint: Some(Color::Yellow),
+                success: Some(Color::Green),
+                danger: Some(Color::Red),
+                warning: Some(Color::Yellow),
+                name: Some(Color::Cyan),
+            }),
+            "light" => Some(Self {
+                accent: Some(Color::Blue),
+                query: Some(Color::Magenta),
+                text: Some(Color::Black),
+                selected: Some(Color::Blue),
+                highlight_fg: Some(Color::White),
+                highlight_bg: Some(Color::Blue),
+                hint: Some(Color::DarkGray),
+                success: Some(Color::Green),
+                danger: Some(Color::Red),
+                warning: Some(Color::Magenta),
+                name: Some(Color::Blue),
+            }),
+            "high-contrast" => Some(Self {
+                accent: Some(Color::LightGreen),
+                query: Some(Color::LightYellow),
+                text: Some(Color::White),
+                selected: Some(Color::LightYellow),
+                highlight_fg: Some(Color::Black),
+                highlight_bg: Some(Color::LightYellow),
+                hint: Some(Color::White),
+                success: Some(Color::LightGreen),
+                danger: Some(Color::LightRed),
+                warning: Some(Color::LightYellow),
+                name: Some(Color::LightCyan),
+            }),
+            "monochrome" => Some(Self::default()),
+            _ => None,
+        }
+    }
+
+    fn resolve(name: &str, config: &Config, depth: usize) -> Result<Self> {
+        if depth > 8 {
+            bail!("Theme '{}' has a cyclic base", name);
+        }
+
+        let Some(custom) = config.themes.get(name) else {
+            return Self::builtin(name).context(format!("Unknown theme: {}", name));
+        };
+
+        // A theme built on its own name, such as `[themes.default]`, extends
+        // the built-in theme of that name rather than itself
+        let base = custom.base.as_deref().unwrap_or("default");
+        let mut theme = if base == name {
+            Self::builtin(base).context(format!("Unknown base theme '{}' for theme '{}'", base, name))?
+        } else {
+            Self::resolve(base, config, depth + 1)?
+        };
+        let overrides = [
+            (&mut theme.accent, &custom.accent),
+            (&mut theme.query, &custom.query),
+            (&mut theme.text, &custom.text),
+            (&mut theme.selected, &custom.selected),
+            (&mut theme.highlight_fg, &custom.highlight_fg),
+            (&mut theme.highlight_bg, &custom.highlight_bg),
+            (&mut theme.hint, &custom.hint),
+            (&mut theme.success, &custom.success),
+            (&mut theme.danger, &custom.danger),
+            (&mut theme.warning, &custom.warning),
+            (&mut theme.name, &custom.name),
+        ];
+        for (slot, value) in overrides {
+            if let Some(value) = value {
+                *slot = match value.as_str() {
+                    "none" => None,
+                    _ => Some(value.parse().map_err(|_| {
+                        anyhow::anyhow!("Invalid color '{}' in theme '{}'", value, name)
+                    })?),
+                };
+            }
+        }
+
+        Ok(theme)
+    }
+
+    fn color(&self, role: Role) -> Option<Color> {
+        match role {
+            Role::Accent => self.accent,
+            Role::Query => self.query,
+            Role::Text => self.text,
+            Role::Selected => self.selected,
+            Role::Hint => self.hint,
+            Role::Success => self.success,
+            Role::Danger => self.danger,
+            Role::Warning => self.warning,
+            Role::Name => self.name,
+        }
+    }
+
+    /// Style for a role in the TUI.
+    pub fn style(&self, role: Role) -> Style {
+        match self.color(role) {
+            Some(color) => Style::default().fg(color),
+            None => Style::default(),
+        }
+    }
+
+    /// Style of the highlighted row. Without colors the row is reversed so
+    /// the selection stays visible.
+    pub fn highlight(&self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match (self.highlight_fg, self.highlight_bg) {
+            (None, None) => style.add_modifier(Modifier::REVERSED),
+            (fg, bg) => {
+                let style = fg.map_or(style, |fg| style.fg(fg));
+                bg.map_or(style, |bg| style.bg(bg))
+            }
+        }
+    }
+
+    /// Colors text for CLI output.
+    pub fn paint(&self, text: &str, role: Role) -> ColoredString {
+        match self.color(role).and_then(to_terminal_color) {
+            Some(color) => text.color(color),
+            None => text.normal(),
+        }
+    }
+}
+
+fn to_terminal_color(color: Color) -> Option<colored::Color> {
+    use colored::Color as C;
+
+    Some(match color {
+        Color::Black => C::Black,
+        Color::Red => C::Red,
+        Color::Green => C::Green,
+        Color::Yellow => C::Yellow,
+        Color::Blue => C::Blue,
+        Color::Magenta => C::Magenta,
+        Color::Cyan => C::Cyan,
+        Color::Gray => C::White,
+        Color::DarkGray => C::BrightBlack,
+        Color::LightRed => C::BrightRed,
+        Color::LightGreen => C::BrightGreen,
+        Color::LightYellow => C::BrightYellow,
+        Color::LightBlue => C::BrightBlue,
+        Color::LightMagenta => C::BrightMagenta,
+        Color::LightCyan => C::BrightCyan,
+        Color::White => C::BrightWhite,
+        Color::Rgb(r, g, b) => C::TrueColor { r, g, b },
+        Color::Reset | Color::Indexed(_) => return None,
+    })
+}
+
+/// Selects the theme for this process from the config and `--color`.
+pub fn init(config: &Config, choice: ColorChoice) -> Result<()> {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && std::io::stdout().is_terminal()
+        }
+    };
+
+    colored::control::set_override(enabled);
+
+    // Resolve even when colors are off so config mistakes are still reported
+    let theme = Theme::resolve(config.theme.as_deref().unwrap_or("default"), config, 0)?;
+    let theme = if enabled { theme } else { Theme::default() };
+
+    let _ = CURRENT.set(theme);
+    Ok(())
+}
+
+/// The active theme, falling back to `default` if [`init`] was not called.
+pub fn current() -> &'static Theme {
+    CURRENT.get_or_init(|| Theme::builtin("default").expect("default theme exists"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn resolves_builtin_themes() {
+        let theme = Theme::resolve("light", &Config::default(), 0).unwrap();
+        assert_eq!(theme.color(Role::Accent), Some(Color::Blue));
+        assert!(Theme::resolve("solarized", &Config::default(), 0).is_err());
+    }
+
+    #[test]
+    fn custom_themes_extend_their_base() {
+        let config = config(
+            r##"
+            [themes.dusk]
+            base = "light"
+            accent = "#ff8800"
+            hint = "none"
+            "##,
+        );
+        let theme = Theme::resolve("dusk", &config, 0).unwrap();
+        assert_eq!(theme.color(Role::Accent), Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(theme.color(Role::Hint), None);
+        assert_eq!(theme.color(Role::Warning), Some(Color::Magenta));
+    }
+
+    #[test]
+    fn a_theme_named_after_a_builtin_extends_it() {
+        let config = config(
+            r#"
+            [themes.default]
+            accent = "red"
+            "#,
+        );
+        let theme = Theme::resolve("default", &config, 0).unwrap();
+        assert_eq!(theme.color(Role::Accent), Some(Color::Red));
+        assert_eq!(theme.color(Role::Name), Some(Color::Cyan));
+    }
+
+    #[test]
+    fn rejects_cycles_and_bad_colors() {
+        let config = config(
+            r#"
+            [themes.day]
+            base = "night"
+            [themes.night]
+            base = "day"
+            [themes.bad]
+            accent = "not-a-color"
+            "#,
+        );
+        let err = Theme::resolve("day", &config, 0).unwrap_err().to_string();
+        assert!(err.contains("cyclic base"), "{}", err);
+        assert!(Theme::resolve("bad", &config, 0).is_err());
+    }
+}
@@ -0,0 +1,1186 @@
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame, Terminal,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{stdout, Stdout},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    backend::{self, OpenMode, OpenOptions},
+    config::Config,
+    frecency::Frecency,
+    force_kill_specific_application, get_app_icon, get_running_applications,
+    history::{KillHistory, KillRecord},
+    keymap::{Action, Keymap},
+    kill_specific_application, open_specific_application,
+    pins::Pins,
+    protect::ProtectedApps,
+    restart::{self, Phase},
+    theme::{self, Role},
+};
+
+// Rows taken by the margin, header, footer and list borders
+const CHROME_HEIGHT: u16 = 10;
+
+pub enum Mode {
+    Normal,
+    Search,
+}
+
+enum ActionStatus {
+    None,
+    Opened(String),
+    Killed(String),
+    Batch(Batch),
+    Notice(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BatchKind {
+    Open(OpenMode),
+    Kill,
+    ForceKill,
+    Hide,
+    Unhide,
+    HideOthers,
+    Minimize,
+}
+
+impl BatchKind {
+    fn progress_verb(self) -> &'static str {
+        match self {
+            BatchKind::Open(_) => "Opening",
+            BatchKind::Kill => "Killing",
+            BatchKind::ForceKill => "Force killing",
+            BatchKind::Hide => "Hiding",
+            BatchKind::Unhide => "Showing",
+            BatchKind::HideOthers => "Hiding all but",
+            BatchKind::Minimize => "Minimizing",
+        }
+    }
+
+    fn done_verb(self) -> &'static str {
+        match self {
+            BatchKind::Open(_) => "opened",
+            BatchKind::Kill => "terminated",
+            BatchKind::ForceKill => "force killed",
+            BatchKind::Hide => "hidden",
+            BatchKind::Unhide => "shown",
+            BatchKind::HideOthers => "kept visible",
+            BatchKind::Minimize => "minimized",
+        }
+    }
+
+    fn apply(self, app_name: &str) -> Result<()> {
+        match self {
+            BatchKind::Open(mode) => open_specific_application(app_name, &OpenOptions::with_mode(mode)),
+            BatchKind::Kill => kill_specific_application(app_name),
+            BatchKind::ForceKill => force_kill_specific_application(app_name),
+            BatchKind::Hide => backend::current().hide(app_name),
+            BatchKind::Unhide => backend::current().unhide(app_name),
+            BatchKind::HideOthers => backend::current().hide_others(app_name),
+            BatchKind::Minimize => backend::current().minimize(app_name),
+        }
+    }
+}
+
+/// An action applied to every marked app, one app per frame so the footer
+/// can show progress.
+struct Batch {
+    kind: BatchKind,
+    pending: VecDeque<String>,
+    total: usize,
+    succeeded: Vec<String>,
+    failed: Vec<(String, String)>,
+    /// When the kill being undone happened, so apps that fail to relaunch
+    /// can be put back in the history
+    undoing: Option<u64>,
+}
+
+impl Batch {
+    fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// The ways an app can be stopped from the list.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KillKind {
+    Kill,
+    ForceKill,
+    Restart,
+}
+
+impl KillKind {
+    fn verb(self) -> &'static str {
+        match self {
+            KillKind::Kill => "Kill",
+            KillKind::ForceKill => "Force kill",
+            KillKind::Restart => "Restart",
+        }
+    }
+}
+
+enum RestartUpdate {
+    Phase(Phase),
+    Finished(Result<(), String>),
+}
+
+/// A restart running on a worker thread, reporting back over a channel.
+struct RunningRestart {
+    app_name: String,
+    updates: Receiver<RestartUpdate>,
+}
+
+/// A kill waiting for confirmation in the dialog.
+struct PendingKill {
+    kind: KillKind,
+    targets: Vec<String>,
+    // Targets on the protected list, which need the explicit override
+    protected: Vec<String>,
+    // Whether the targets came from marks and should run as a batch
+    batch: bool,
+}
+
+struct AppState {
+    apps: Vec<String>,
+    installed_apps: Vec<String>,
+    filtered_apps: Vec<String>,
+    // Leading entries of `filtered_apps` that are pinned
+    favorites_count: usize,
+    frecency: Frecency,
+    pins: Pins,
+    selected_index: usize,
+    mode: Mode,
+    search_query: String,
+    should_quit: bool,
+    action_status: ActionStatus,
+    status_counter: u8,
+    marked: HashSet<String>,
+    pending_kill: Option<PendingKill>,
+    restart: Option<RunningRestart>,
+    protected: ProtectedApps,
+    confirm_kill: bool,
+    escalate_after: Duration,
+    // Standalone `qapp open` picker: leave after opening or cancelling
+    search_only: bool,
+    // What plain Open does, as chosen on the command line
+    open_options: OpenOptions,
+    page_size: usize,
+}
+
+impl AppState {
+    fn new(running_apps: Vec<String>) -> Self {
+        Self {
+            apps: running_apps,
+            installed_apps: Vec::new(),
+            filtered_apps: Vec::new(),
+            favorites_count: 0,
+            frecency: Frecency::default(),
+            pins: Pins::load().unwrap_or_default(),
+            selected_index: 0,
+            mode: Mode::Normal,
+            search_query: String::new(),
+            should_quit: false,
+            action_status: ActionStatus::None,
+            status_counter: 0,
+            marked: HashSet::new(),
+            pending_kill: None,
+            restart: None,
+            protected: ProtectedApps::default(),
+            confirm_kill: false,
+            escalate_after: Duration::from_secs(10),
+            search_only: false,
+            open_options: OpenOptions::default(),
+            page_size: 10,
+        }
+    }
+
+    fn set_opened(&mut self, app_name: String) {
+        self.action_status = ActionStatus::Opened(app_name);
+        self.status_counter = 30; // Show for ~3 seconds (at 100ms per frame)
+    }
+
+    fn set_killed(&mut self, app_name: String) {
+        self.action_status = ActionStatus::Killed(app_name);
+        self.status_counter = 30; // Show for ~3 seconds
+    }
+
+    fn marked_in_order(&self) -> Vec<String> {
+        // Keep the on-screen order rather than the set's
+        self.apps
+            .iter()
+            .filter(|app| self.marked.contains(*app))
+            .cloned()
+            .collect()
+    }
+
+    /// Starts a batch on the marked apps, which are unmarked.
+    fn start_batch(&mut self, kind: BatchKind, targets: Vec<String>) {
+        self.marked.clear();
+        self.begin_batch(kind, targets, None);
+    }
+
+    fn begin_batch(&mut self, kind: BatchKind, targets: Vec<String>, undoing: Option<u64>) {
+        let pending = VecDeque::from(targets);
+        if pending.is_empty() {
+            return;
+        }
+
+        self.action_status = ActionStatus::Batch(Batch {
+            kind,
+            total: pending.len(),
+            pending,
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            undoing,
+        });
+        self.status_counter = 0;
+    }
+
+    /// Applies the batch action to the next pending app, if any.
+    fn step_batch(&mut self) {
+        let ActionStatus::Batch(batch) = &mut self.action_status else {
+            return;
+        };
+        let Some(app_name) = batch.pending.pop_front() else {
+            return;
+        };
+
+        match batch.kind.apply(&app_name) {
+            Ok(()) => batch.succeeded.push(app_name),
+            Err(err) => batch.failed.push((app_name, err.to_string())),
+        }
+
+        if batch.is_done() {
+            if matches!(batch.kind, BatchKind::Kill | BatchKind::ForceKill) {
+                // Undo is best effort; a failed write shouldn't end the session
+                let _ = KillHistory::record(&batch.succeeded);
+            }
+            if let Some(killed_at) = batch.undoing {
+                let apps = batch.failed.iter().map(|(app_name, _)| app_name.clone()).collect();
+                let _ = KillHistory::restore(KillRecord { apps, killed_at });
+            }
+            self.status_counter = 50; // Show the summary for ~5 seconds
+            self.refresh_running_apps();
+        }
+    }
+
+    /// Kills the marked apps, or the selected one, asking first when
+    /// confirmation is enabled or a target is protected.
+    fn request_kill(&mut self, kind: KillKind) -> Result<()> {
+        // Restarts always apply to the selected app only
+        let batch = !self.marked.is_empty() && kind != KillKind::Restart;
+        let targets = if batch {
+            self.marked_in_order()
+        } else {
+            self.selected_app().cloned().into_iter().collect()
+        };
+
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let protected: Vec<String> = targets
+            .iter()
+            .filter(|app| self.protected.contains(app))
+            .cloned()
+            .collect();
+
+        let pending = PendingKill { kind, targets, protected, batch };
+        if self.confirm_kill || !pending.protected.is_empty() {
+            self.pending_kill = Some(pending);
+            Ok(())
+        } else {
+            self.kill(pending)
+        }
+    }
+
+    fn kill(&mut self, pending: PendingKill) -> Result<()> {
+        let kind = match pending.kind {
+            KillKind::Kill => BatchKind::Kill,
+            KillKind::ForceKill => BatchKind::ForceKill,
+            KillKind::Restart => {
+                if let Some(app_name) = pending.targets.into_iter().next() {
+                    self.start_restart(app_name);
+                }
+                return Ok(());
+            },
+        };
+
+        if pending.batch {
+            self.start_batch(kind, pending.targets);
+            return Ok(());
+        }
+
+        let mut killed = Vec::new();
+        for app_name in pending.targets {
+            // A refusing hook or backend is reported without leaving the list
+            match kind.apply(&app_name) {
+                Ok(()) => {
+                    self.set_killed(app_name.clone());
+                    killed.push(app_name);
+                },
+                Err(err) => {
+                    self.action_status = ActionStatus::Notice(format!("⚠️ Killing {} failed: {}", app_name, err));
+                    self.status_counter = 50;
+                },
+            }
+        }
+
+        // Undo is best effort; a failed write shouldn't end the session
+        let _ = KillHistory::record(&killed);
+
+        // Refresh the list of running apps
+        self.refresh_running_apps();
+        Ok(())
+    }
+
+    fn start_restart(&mut self, app_name: String) {
+        let (sender, updates) = mpsc::channel();
+        let escalate_after = self.escalate_after;
+        let thread_app_name = app_name.clone();
+
+        thread::spawn(move || {
+            let result = restart::restart(&thread_app_name, escalate_after, |phase| {
+                let _ = sender.send(RestartUpdate::Phase(phase));
+            });
+            let _ = sender.send(RestartUpdate::Finished(result.map_err(|err| err.to_string())));
+        });
+
+        self.restart = Some(RunningRestart { app_name, updates });
+    }
+
+    /// Shows the progress of a running restart in the footer.
+    fn poll_restart(&mut self) {
+        let Some(running) = &self.restart else {
+            return;
+        };
+
+        let mut finished = false;
+        while let Ok(update) = running.updates.try_recv() {
+            let message = match update {
+                RestartUpdate::Phase(phase) => {
+                    self.status_counter = 0;
+                    format!("🔄 {}: {}", running.app_name, phase.describe())
+                },
+                RestartUpdate::Finished(result) => {
+                    finished = true;
+                    self.status_counter = 50;
+                    match result {
+                        Ok(()) => format!("✅ {} restarted", running.app_name),
+                        Err(err) => format!("⚠️ Restarting {} failed: {}", running.app_name, err),
+                    }
+                },
+            };
+            self.action_status = ActionStatus::Notice(message);
+        }
+
+        if finished {
+            self.restart = None;
+            self.refresh_running_apps();
+        }
+    }
+
+    /// Relaunches the apps from the most recent kill.
+    fn undo_kill(&mut self) -> Result<()> {
+        match KillHistory::pop()? {
+            // Marks are left alone, since undo doesn't act on them
+            Some(record) => self.begin_batch(BatchKind::Open(OpenMode::Focus), record.apps, Some(record.killed_at)),
+            None => {
+                self.action_status = ActionStatus::Notice("Nothing to undo".to_string());
+                self.status_counter = 30;
+            },
+        }
+        Ok(())
+    }
+
+    /// Applies a window action to the marked apps, or the selected one.
+    fn change_visibility(&mut self, kind: BatchKind) {
+        if !self.marked.is_empty() && kind != BatchKind::HideOthers {
+            let targets = self.marked_in_order();
+            self.start_batch(kind, targets);
+            return;
+        }
+
+        let Some(app_name) = self.selected_app().cloned() else {
+            return;
+        };
+
+        let message = match kind.apply(&app_name) {
+            Ok(()) => format!("{} {}", app_name, kind.done_verb()),
+            Err(err) => err.to_string(),
+        };
+        self.action_status = ActionStatus::Notice(message);
+        self.status_counter = 30;
+    }
+
+    fn toggle_pin(&mut self) {
+        let Some(app_name) = self.selected_app().cloned() else {
+            return;
+        };
+
+        let message = match self.pins.toggle(&app_name) {
+            Ok(true) => format!("★ Pinned {}", app_name),
+            Ok(false) => format!("Unpinned {}", app_name),
+            Err(err) => format!("Failed to update pins: {}", err),
+        };
+        self.action_status = ActionStatus::Notice(message);
+        self.status_counter = 30;
+
+        if let Mode::Search = self.mode {
+            self.filter_installed_apps();
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(app_name) = self.selected_app().cloned()
+            && !self.marked.remove(&app_name)
+        {
+            self.marked.insert(app_name);
+        }
+    }
+
+    fn update_status(&mut self) {
+        if self.status_counter > 0 {
+            self.status_counter -= 1;
+            if self.status_counter == 0 {
+                self.action_status = ActionStatus::None;
+            }
+        }
+    }
+
+    fn load_installed_apps(&mut self) -> Result<()> {
+        self.installed_apps = backend::current().installed_apps()?;
+        self.filter_installed_apps();
+        Ok(())
+    }
+
+    fn refresh_running_apps(&mut self) {
+        if let Ok(updated_apps) = get_running_applications() {
+            self.marked.retain(|app| updated_apps.contains(app));
+            self.apps = updated_apps;
+            self.selected_index = self.selected_index.min(self.apps.len().saturating_sub(1));
+        }
+    }
+
+    fn filter_installed_apps(&mut self) {
+        let query = self.search_query.to_lowercase();
+        let matches = |app: &String| app.to_lowercase().contains(&query);
+
+        // Pinned apps come first in pinning order, even ones outside /Applications
+        let mut filtered: Vec<String> = self.pins
+            .apps()
+            .iter()
+            .filter(|app| matches(app))
+            .cloned()
+            .collect();
+        self.favorites_count = filtered.len();
+
+        let mut others: Vec<String> = self.installed_apps
+            .iter()
+            .filter(|app| matches(app) && !self.pins.contains(app))
+            .cloned()
+            .collect();
+
+        // Most frequently and recently launched apps first
+        self.frecency.rank(&mut others);
+
+        filtered.extend(others);
+        self.filtered_apps = filtered;
+
+        // Reset selection if filtered list changes
+        if !self.filtered_apps.is_empty() {
+            self.selected_index = self.selected_index.min(self.filtered_apps.len() - 1);
+        } else {
+            self.selected_index = 0;
+        }
+    }
+
+    fn visible_apps(&self) -> &[String] {
+        match self.mode {
+            Mode::Normal => &self.apps,
+            Mode::Search => &self.filtered_apps,
+        }
+    }
+
+    fn next(&mut self) {
+        let len = self.visible_apps().len();
+        if len > 0 {
+            self.selected_index = (self.selected_index + 1) % len;
+        }
+    }
+
+    fn previous(&mut self) {
+        let len = self.visible_apps().len();
+        if len > 0 {
+            self.selected_index = (self.selected_index + len - 1) % len;
+        }
+    }
+
+    fn first(&mut self) {
+        self.selected_index = 0;
+    }
+
+    fn last(&mut self) {
+        self.selected_index = self.visible_apps().len().saturating_sub(1);
+    }
+
+    fn page_down(&mut self) {
+        let last = self.visible_apps().len().saturating_sub(1);
+        self.selected_index = (self.selected_index + self.page_size).min(last);
+    }
+
+    fn page_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(self.page_size);
+    }
+
+    fn selected_app(&self) -> Option<&String> {
+        self.visible_apps().get(self.selected_index)
+    }
+
+    fn add_to_search(&mut self, c: char) {
+        self.search_query.push(c);
+        self.filter_installed_apps();
+    }
+
+    fn backspace_search(&mut self) {
+        self.search_query.pop();
+        self.filter_installed_apps();
+    }
+
+    fn enter_search_mode(&mut self) -> Result<()> {
+        if self.installed_apps.is_empty() {
+            self.load_installed_apps()?;
+        }
+
+        // Pick up launches made since the last search
+        self.frecency = Frecency::load().unwrap_or_default();
+
+        self.mode = Mode::Search;
+        self.search_query.clear();
+        self.filter_installed_apps();
+        self.selected_index = 0;
+        Ok(())
+    }
+
+    fn exit_search_mode(&mut self) {
+        self.mode = Mode::Normal;
+        self.search_query.clear();
+        self.selected_index = 0;
+    }
+}
+
+pub fn interactive_app_list(config: &Config) -> Result<()> {
+    let keymap = Keymap::from_config(&config.keys)?;
+
+    // Get running apps
+    let apps = get_running_applications()?;
+
+    if apps.is_empty() {
+        println!("{}", theme::current().paint("No visible applications found.", Role::Warning));
+        return Ok(());
+    }
+
+    // Create app state
+    let mut app_state = AppState::new(apps);
+    app_state.protected = ProtectedApps::load(&config.kill);
+    app_state.confirm_kill = config.kill.confirm;
+    app_state.escalate_after = config.kill.escalate_after;
+
+    // Preload installed apps in the background
+    app_state.load_installed_apps()?;
+
+    run(&mut app_state, &keymap)
+}
+
+/// Fuzzy search over installed applications, opening the chosen one.
+pub fn search_and_open(open_options: &OpenOptions, config: &Config) -> Result<()> {
+    let keymap = Keymap::from_config(&config.keys)?;
+
+    // Create app state in search mode
+    let mut app_state = AppState::new(vec![]);
+    app_state.search_only = true;
+    app_state.open_options = open_options.clone();
+    app_state.enter_search_mode()?;
+
+    run(&mut app_state, &keymap)
+}
+
+fn run(app_state: &mut AppState, keymap: &Keymap) -> Result<()> {
+    // Setup terminal
+    terminal::enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let result = event_loop(&mut terminal, app_state, keymap);
+
+    // Clean up terminal
+    terminal::disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app_state: &mut AppState,
+    keymap: &Keymap,
+) -> Result<()> {
+    // Application loop
+    while !app_state.should_quit {
+        app_state.page_size = terminal.size()?.height.saturating_sub(CHROME_HEIGHT).max(1) as usize;
+
+        terminal.draw(|frame| draw(frame, app_state, keymap))?;
+
+        // Update action status counter and advance any running batch
+        app_state.update_status();
+        app_state.step_batch();
+        app_state.poll_restart();
+
+        // Handle input
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            handle_key(app_state, keymap, key)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_key(app_state: &mut AppState, keymap: &Keymap, key: KeyEvent) -> Result<()> {
+    // The confirmation dialog takes all input while it is open
+    if let Some(pending) = app_state.pending_kill.take() {
+        return match keymap.lookup_confirm(&key) {
+            Some(Action::Confirm) if pending.protected.is_empty() => app_state.kill(pending),
+            Some(Action::ConfirmProtected) => app_state.kill(pending),
+            Some(Action::Cancel) => Ok(()),
+            _ => {
+                app_state.pending_kill = Some(pending);
+                Ok(())
+            }
+        };
+    }
+
+    let Some(action) = keymap.lookup(&app_state.mode, &key) else {
+        // Unbound printable keys edit the query in search mode
+        if let (Mode::Search, KeyCode::Char(c)) = (&app_state.mode, key.code)
+            && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            app_state.add_to_search(c);
+        }
+        return Ok(());
+    };
+
+    // Ignore further actions until a running batch has finished
+    if let ActionStatus::Batch(batch) = &app_state.action_status
+        && !batch.is_done()
+        && action != Action::Quit
+    {
+        return Ok(());
+    }
+
+    match action {
+        Action::Quit => {
+            app_state.should_quit = true;
+        },
+        Action::Up => app_state.previous(),
+        Action::Down => app_state.next(),
+        Action::Top => app_state.first(),
+        Action::Bottom => app_state.last(),
+        Action::PageUp => app_state.page_up(),
+        Action::PageDown => app_state.page_down(),
+        Action::Open | Action::OpenNew | Action::OpenBackground => {
+            let mut options = app_state.open_options.clone();
+            options.mode = match action {
+                Action::OpenNew => OpenMode::NewInstance,
+                Action::OpenBackground => OpenMode::Background,
+                _ => options.mode,
+            };
+
+            if matches!(app_state.mode, Mode::Normal) && !app_state.marked.is_empty() {
+                let targets = app_state.marked_in_order();
+                app_state.start_batch(BatchKind::Open(options.mode), targets);
+            } else if let Some(app_name) = app_state.selected_app().cloned() {
+                // Open the application, e.g. focus mode may refuse it
+                if let Err(err) = open_specific_application(&app_name, &options) {
+                    app_state.action_status = ActionStatus::Notice(format!("⚠️ Opening {} failed: {}", app_name, err));
+                    app_state.status_counter = 50;
+                    return Ok(());
+                }
+
+                if app_state.search_only {
+                    app_state.should_quit = true;
+                    return Ok(());
+                }
+
+                // Update state with success message and leave search mode
+                app_state.set_opened(app_name);
+                if let Mode::Search = app_state.mode {
+                    app_state.exit_search_mode();
+                }
+
+                // Refresh the list of running apps
+                app_state.refresh_running_apps();
+            }
+        },
+        Action::Kill | Action::ForceKill | Action::Restart => {
+            if let Mode::Normal = app_state.mode
+                && app_state.restart.is_none()
+            {
+                app_state.request_kill(match action {
+                    Action::ForceKill => KillKind::ForceKill,
+                    Action::Restart => KillKind::Restart,
+                    _ => KillKind::Kill,
+                })?;
+            }
+        },
+        Action::Search => {
+            app_state.enter_search_mode()?;
+        },
+        Action::Refresh => match app_state.mode {
+            Mode::Normal => app_state.refresh_running_apps(),
+            Mode::Search => app_state.load_installed_apps()?,
+        },
+        Action::Cancel => {
+            if app_state.search_only {
+                app_state.should_quit = true;
+            } else {
+                app_state.exit_search_mode();
+            }
+        },
+        Action::DeleteChar => {
+            app_state.backspace_search();
+        },
+        Action::ToggleMark => {
+            if let Mode::Normal = app_state.mode {
+                app_state.toggle_mark();
+                app_state.next();
+            }
+        },
+        Action::ClearMarks => {
+            app_state.marked.clear();
+        },
+        Action::Undo => {
+            if let Mode::Normal = app_state.mode {
+                app_state.undo_kill()?;
+            }
+        },
+        Action::TogglePin => {
+            app_state.toggle_pin();
+        },
+        Action::Hide | Action::Unhide | Action::HideOthers | Action::Minimize => {
+            if let Mode::Normal = app_state.mode {
+                app_state.change_visibility(match action {
+                    Action::Hide => BatchKind::Hide,
+                    Action::Unhide => BatchKind::Unhide,
+                    Action::HideOthers => BatchKind::HideOthers,
+                    _ => BatchKind::Minimize,
+                });
+            }
+        },
+        Action::Confirm | Action::ConfirmProtected => {},
+    }
+
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, app_state: &AppState, keymap: &Keymap) {
+    let theme = theme::current();
+
+    // Create layout for the UI
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3),  // Header
+            Constraint::Min(5),     // List of apps
+            Constraint::Length(3),  // Footer
+        ])
+        .split(frame.area());
+
+    // Header - changes based on mode
+    let header_content = match app_state.mode {
+        Mode::Normal => Line::from(vec![
+            Span::styled(
+                "Running Applications",
+                theme.style(Role::Accent).add_modifier(Modifier::BOLD),
+            )
+        ]),
+        Mode::Search => Line::from(vec![
+            Span::styled(
+                "Search Applications: ",
+                theme.style(Role::Accent).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                &app_state.search_query,
+                theme.style(Role::Query).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                "_",  // Cursor
+                theme.style(Role::Text).add_modifier(Modifier::BOLD),
+            ),
+        ]),
+    };
+
+    let header = Paragraph::new(header_content)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+    frame.render_widget(header, chunks[0]);
+
+    // List of apps - changes based on mode
+    let mut app_items: Vec<ListItem> = app_state
+        .visible_apps()
+        .iter()
+        .enumerate()
+        .map(|(i, app)| {
+            let icon = get_app_icon(app);
+            let mut spans = Vec::new();
+            // Reserve the marker column only while something is marked
+            if !app_state.marked.is_empty() {
+                spans.push(if app_state.marked.contains(app) {
+                    Span::styled("● ", theme.style(Role::Accent))
+                } else {
+                    Span::raw("  ")
+                });
+            }
+            spans.extend([
+                Span::raw(format!("{} ", icon)),
+                Span::styled(
+                    app.clone(),
+                    theme.style(if i == app_state.selected_index {
+                        Role::Selected
+                    } else {
+                        Role::Text
+                    })
+                ),
+            ]);
+            // Favorites get their own section in search mode
+            if let Mode::Normal = app_state.mode
+                && app_state.pins.contains(app)
+            {
+                spans.push(Span::styled(" ★", theme.style(Role::Accent)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    // Section headers shift the selected row down
+    let mut selected_row = app_state.selected_index;
+    if let Mode::Search = app_state.mode
+        && app_state.favorites_count > 0
+    {
+        let section = |title: &'static str| {
+            ListItem::new(Line::from(Span::styled(
+                title,
+                theme.style(Role::Accent).add_modifier(Modifier::BOLD),
+            )))
+        };
+
+        let others = app_items.split_off(app_state.favorites_count);
+        app_items.insert(0, section("★ Favorites"));
+        selected_row += 1;
+
+        if !others.is_empty() {
+            app_items.push(section("Applications"));
+            app_items.extend(others);
+            if app_state.selected_index >= app_state.favorites_count {
+                selected_row += 1;
+            }
+        }
+    }
+
+    let list_title = match app_state.mode {
+        Mode::Normal if !app_state.marked.is_empty() => {
+            format!("Running Applications ({} marked)", app_state.marked.len())
+        },
+        Mode::Normal => "Running Applications".to_string(),
+        Mode::Search => if app_state.filtered_apps.is_empty() {
+            "No matching applications".to_string()
+        } else {
+            "Matching Applications".to_string()
+        },
+    };
+
+    let apps_list = List::new(app_items)
+        .block(Block::default()
+            .title(list_title)
+            .borders(Borders::ALL))
+        .highlight_style(theme.highlight())
+        .highlight_symbol("➤ ");
+
+    frame.render_stateful_widget(
+        apps_list,
+        chunks[1],
+        &mut ratatui::widgets::ListState::default().with_selected(Some(selected_row)),
+    );
+
+    // Footer with keybindings or status message
+    let footer_content = match app_state.action_status {
+        ActionStatus::None => {
+            // Show the active keybindings
+            Line::from(vec![
+                Span::styled(
+                    keymap.hints(&app_state.mode),
+                    theme.style(Role::Hint)
+                )
+            ])
+        },
+        ActionStatus::Opened(ref app_name) => {
+            // Show opened confirmation
+            Line::from(vec![
+                Span::styled(
+                    "✅ ",
+                    theme.style(Role::Success)
+                ),
+                Span::styled(
+                    app_name.clone(),
+                    theme.style(Role::Success).add_modifier(Modifier::BOLD)
+                ),
+                Span::styled(
+                    " opened",
+                    theme.style(Role::Success)
+                )
+            ])
+        },
+        ActionStatus::Killed(ref app_name) => {
+            // Show killed confirmation
+            Line::from(vec![
+                Span::styled(
+                    "❌ ",
+                    theme.style(Role::Danger)
+                ),
+                Span::styled(
+                    app_name.clone(),
+                    theme.style(Role::Danger).add_modifier(Modifier::BOLD)
+                ),
+                Span::styled(
+                    " terminated",
+                    theme.style(Role::Danger)
+                )
+            ])
+        },
+        ActionStatus::Notice(ref message) => {
+            Line::from(vec![
+                Span::styled(
+                    message.clone(),
+                    theme.style(Role::Warning)
+                )
+            ])
+        },
+        ActionStatus::Batch(ref batch) if !batch.is_done() => {
+            // Show which app is being worked on
+            let current = batch.pending.front().map(String::as_str).unwrap_or_default();
+            Line::from(vec![
+                Span::styled(
+                    format!("⏳ {} {}/{}: ", batch.kind.progress_verb(), batch.total - batch.pending.len() + 1, batch.total),
+                    theme.style(Role::Warning)
+                ),
+                Span::styled(
+                    current.to_string(),
+                    theme.style(Role::Warning).add_modifier(Modifier::BOLD)
+                ),
+            ])
+        },
+        ActionStatus::Batch(ref batch) => {
+            // Summarize what succeeded and what failed
+            let mut spans = Vec::new();
+            if !batch.succeeded.is_empty() {
+                spans.push(Span::styled(
+                    format!("✅ {} {}", batch.succeeded.join(", "), batch.kind.done_verb()),
+                    theme.style(Role::Success)
+                ));
+            }
+            if !batch.failed.is_empty() {
+                if !spans.is_empty() {
+                    spans.push(Span::raw("   "));
+                }
+                let failures: Vec<String> = batch.failed
+                    .iter()
+                    .map(|(app_name, err)| format!("{} ({})", app_name, err))
+                    .collect();
+                spans.push(Span::styled(
+                    format!("⚠️ failed: {}", failures.join(", ")),
+                    theme.style(Role::Danger).add_modifier(Modifier::BOLD)
+                ));
+            }
+            Line::from(spans)
+        },
+    };
+
+    let footer = Paragraph::new(footer_content)
+        .alignment(Alignment::Left)
+        .block(Block::default().borders(Borders::ALL));
+
+    frame.render_widget(footer, chunks[2]);
+
+    if let Some(pending) = &app_state.pending_kill {
+        draw_kill_dialog(frame, pending, keymap);
+    }
+}
+
+fn draw_kill_dialog(frame: &mut Frame, pending: &PendingKill, keymap: &Keymap) {
+    let theme = theme::current();
+
+    let verb = pending.kind.verb();
+    let question = match pending.targets.as_slice() {
+        [app_name] => format!("{} {}?", verb, app_name),
+        targets => format!("{} {} apps: {}?", verb, targets.len(), targets.join(", ")),
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(question, theme.style(Role::Text).add_modifier(Modifier::BOLD))),
+    ];
+    if !pending.protected.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("⚠️ Protected: {}", pending.protected.join(", ")),
+            theme.style(Role::Danger).add_modifier(Modifier::BOLD),
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        keymap.confirm_hints(!pending.protected.is_empty()),
+        theme.style(Role::Hint),
+    )));
+
+    let area = centered_rect(frame.area(), 60, lines.len() as u16 + 3);
+    let dialog = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .block(Block::default()
+            .title("Confirm")
+            .borders(Borders::ALL)
+            .border_style(theme.style(Role::Danger)));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(dialog, area);
+}
+
+/// A rectangle `percent_x` wide and `height` tall in the middle of `area`.
+fn centered_rect(area: Rect, percent_x: u16, height: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store;
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn toggles_marks_on_the_selected_app() {
+        let _sandbox = backend::sandbox();
+        let mut state = AppState::new(strings(&["Finder", "Terminal", "Slack"]));
+
+        state.selected_index = 2;
+        state.toggle_mark();
+        state.selected_index = 0;
+        state.toggle_mark();
+        assert_eq!(state.marked_in_order(), ["Finder", "Slack"]);
+
+        state.toggle_mark();
+        assert_eq!(state.marked_in_order(), ["Slack"]);
+    }
+
+    #[test]
+    fn batch_kills_marked_apps_one_per_step() {
+        let _sandbox = backend::sandbox();
+        backend::current().open("Slack", &OpenOptions::default()).unwrap();
+        let mut state = AppState::new(get_running_applications().unwrap());
+        state.marked = HashSet::from(["Slack".to_string(), "Terminal".to_string()]);
+
+        state.request_kill(KillKind::Kill).unwrap();
+        assert!(state.marked.is_empty());
+
+        state.step_batch();
+        let ActionStatus::Batch(batch) = &state.action_status else {
+            panic!("no batch running");
+        };
+        assert_eq!(batch.succeeded, ["Terminal"]);
+        assert!(!batch.is_done());
+
+        state.step_batch();
+        assert_eq!(state.apps, ["Finder"]);
+        assert_eq!(KillHistory::pop().unwrap().unwrap().apps, ["Terminal", "Slack"]);
+    }
+
+    #[test]
+    fn refreshing_drops_marks_on_apps_that_quit() {
+        let _sandbox = backend::sandbox();
+        let mut state = AppState::new(strings(&["Finder", "Terminal"]));
+        state.marked = HashSet::from(["Finder".to_string(), "Terminal".to_string()]);
+
+        backend::current().quit("Terminal").unwrap();
+        state.selected_index = 1;
+        state.refresh_running_apps();
+        assert_eq!(state.marked_in_order(), ["Finder"]);
+        assert_eq!(state.selected_index, 0);
+    }
+
+    #[test]
+    fn a_failed_kill_is_reported_in_the_footer() {
+        let _sandbox = backend::sandbox();
+        let mut state = AppState::new(strings(&["Finder", "Terminal", "Slack"]));
+
+        // Slack isn't running in the fake backend, so quitting it fails
+        state.selected_index = 2;
+        state.request_kill(KillKind::Kill).unwrap();
+        let ActionStatus::Notice(message) = &state.action_status else {
+            panic!("no notice shown");
+        };
+        assert!(message.contains("Killing Slack failed"), "{}", message);
+        assert!(KillHistory::pop().unwrap().is_none());
+    }
+
+    #[test]
+    fn a_blocked_open_is_reported_in_the_footer() {
+        let _sandbox = backend::sandbox();
+        let session = serde_json::json!({
+            "session": { "until": store::now() + 3600, "blocked": ["Slack"], "restore": false, "quit": [] }
+        });
+        store::save("focus.json", &session).unwrap();
+        let mut state = AppState::new(strings(&["Finder", "Terminal"]));
+        state.installed_apps = strings(&["Slack"]);
+        state.mode = Mode::Search;
+        state.filtered_apps = strings(&["Slack"]);
+        state.selected_index = 0;
+
+        handle_key(&mut state, &Keymap::default(), KeyEvent::from(KeyCode::Enter)).unwrap();
+        let ActionStatus::Notice(message) = &state.action_status else {
+            panic!("no notice shown");
+        };
+        assert!(message.contains("Opening Slack failed: Slack is blocked by focus mode"), "{}", message);
+        assert!(!state.should_quit);
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    duration, get_running_applications,
+    theme::{self, Role},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The state to wait for an app to reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Until {
+    Running,
+    Exited,
+}
+
+impl Until {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Until::Running => "running",
+            Until::Exited => "exited",
+        }
+    }
+}
+
+fn is_running(app_name: &str) -> Result<bool> {
+    Ok(get_running_applications()?.iter().any(|app| app.eq_ignore_ascii_case(app_name)))
+}
+
+/// Polls the running list until the app is running or has exited, returning
+/// false if `timeout` passes first. Without a timeout, waits for good.
+pub fn until(app_name: &str, state: Until, timeout: Option<Duration>) -> Result<bool> {
+    // A timeout too long to represent never comes
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
+    loop {
+        if is_running(app_name)? == (state == Until::Running) {
+            return Ok(true);
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Fails if the app doesn't reach `state` within `timeout`.
+pub fn expect(app_name: &str, state: Until, timeout: Duration) -> Result<()> {
+    if !until(app_name, state, Some(timeout))? {
+        let still = match state {
+            Until::Running => "still not running",
+            Until::Exited => "still running",
+        };
+        bail!("{} {} after {}", app_name, still, duration::format(timeout));
+    }
+    Ok(())
+}
+
+/// Blocks until the app is running or has exited, for scripts. Fails once
+/// `timeout` has passed, so the exit status tells which happened.
+pub fn run(app_name: &str, state: Until, timeout: Option<Duration>) -> Result<()> {
+    let theme = theme::current();
+
+    match timeout {
+        Some(timeout) => expect(app_name, state, timeout)?,
+        None => {
+            until(app_name, state, None)?;
+        },
+    }
+
+    let label = match state {
+        Until::Running => "Running:",
+        Until::Exited => "Exited:",
+    };
+    println!("{} {}", theme.paint(label, Role::Success), theme.paint(app_name, Role::Name));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend;
+
+    #[test]
+    fn returns_as_soon_as_the_app_is_there() {
+        let _sandbox = backend::sandbox();
+
+        assert!(until("terminal", Until::Running, Some(Duration::ZERO)).unwrap());
+        assert!(until("Slack", Until::Exited, None).unwrap());
+        // Too long to represent, so it never runs out
+        assert!(until("Terminal", Until::Running, Some(Duration::MAX)).unwrap());
+    }
+
+    #[test]
+    fn gives_up_once_the_timeout_passes() {
+        let _sandbox = backend::sandbox();
+
+        let started = Instant::now();
+        assert!(!until("Slack", Until::Running, Some(POLL_INTERVAL)).unwrap());
+        assert!(started.elapsed() >= POLL_INTERVAL);
+
+        let err = expect("Slack", Until::Running, Duration::ZERO).unwrap_err();
+        assert_eq!(err.to_string(), "Slack still not running after 0ms");
+        let err = expect("Terminal", Until::Exited, Duration::ZERO).unwrap_err();
+        assert_eq!(err.to_string(), "Terminal still running after 0ms");
+    }
+
+    #[test]
+    fn fails_the_command_only_on_a_timeout() {
+        let _sandbox = backend::sandbox();
+
+        // An error from `run` is what makes `qapp wait` exit nonzero
+        assert!(run("Terminal", Until::Running, Some(Duration::ZERO)).is_ok());
+        assert!(run("Slack", Until::Exited, None).is_ok());
+        assert!(run("Terminal", Until::Exited, Some(Duration::ZERO)).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    backend::{self, AppInfo},
+    hooks,
+    store,
+    theme::{self, Role},
+};
+
+/// What happened to an application between two looks at the running list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    Started,
+    Exited,
+}
+
+/// One line of `qapp watch` output.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    pub name: String,
+    pub bundle_id: Option<String>,
+    /// The app's main process, when it could be found
+    pub pid: Option<u32>,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+struct Seen {
+    app: AppInfo,
+    pid: Option<u32>,
+}
+
+/// Turns successive running lists into start and exit events.
+pub struct Watcher {
+    seen: BTreeMap<String, Seen>,
+}
+
+impl Watcher {
+    /// Starts from the apps running now, which produce no events.
+    pub fn new() -> Result<Self> {
+        let mut watcher = Self { seen: BTreeMap::new() };
+        watcher.poll()?;
+        Ok(watcher)
+    }
+
+    /// A `started` event for every app running now, as if they had just
+    /// been launched.
+    pub fn existing(&self) -> Vec<Event> {
+        let timestamp = store::now();
+        self.seen
+            .values()
+            .map(|seen| event(EventKind::Started, seen, timestamp))
+            .collect()
+    }
+
+    /// Compares the running list against the previous one.
+    pub fn poll(&mut self) -> Result<Vec<Event>> {
+        let backend = backend::current();
+        let running = backend.running_apps()?;
+        let timestamp = store::now();
+        let mut events = Vec::new();
+
+        let names: Vec<&str> = running.iter().map(|app| app.name.as_str()).collect();
+        let exited: Vec<String> = self
+            .seen
+            .keys()
+            .filter(|name| !names.contains(&name.as_str()))
+            .cloned()
+            .collect();
+        for name in exited {
+            if let Some(seen) = self.seen.remove(&name) {
+                events.push(event(EventKind::Exited, &seen, timestamp));
+            }
+        }
+
+        for app in running {
+            if self.seen.contains_key(&app.name) {
+                continue;
+            }
+
+            // Only looked up once per launch, since the exit event needs it
+            // after the process is gone
+            let pid = backend.process_ids(&app).ok().and_then(|pids| pids.into_iter().min());
+            let seen = Seen { app, pid };
+            events.push(event(EventKind::Started, &seen, timestamp));
+            self.seen.insert(seen.app.name.clone(), seen);
+        }
+
+        Ok(events)
+    }
+}
+
+fn event(kind: EventKind, seen: &Seen, timestamp: u64) -> Event {
+    Event {
+        event: kind,
+        name: seen.app.name.clone(),
+        bundle_id: seen.app.bundle_id.clone(),
+        pid: seen.pid,
+        timestamp,
+    }
+}
+
+/// Prints an event per line as JSON until interrupted or stdout closes,
+/// running the `on-start` and `on-exit` hooks for each.
+pub fn run(interval: Duration, existing: bool) -> Result<()> {
+    let theme = theme::current();
+    let mut watcher = Watcher::new()?;
+
+    // Apps that were already running didn't just start, so they get no hooks
+    let mut pending = if existing { watcher.existing() } else { Vec::new() };
+    let mut run_hooks = false;
+
+    loop {
+        let mut stdout = io::stdout().lock();
+        for event in &pending {
+            let written = writeln!(stdout, "{}", serde_json::to_string(event)?).and_then(|()| stdout.flush());
+            match written {
+                Ok(()) => {},
+                // Whoever was reading has gone away, e.g. `qapp watch | head`
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        drop(stdout);
+
+        if run_hooks {
+            for event in &pending {
+                // Only before hooks can fail, and these never are
+                let _ = hooks::run_for(event.event.into(), &event.into());
+            }
+        }
+        run_hooks = true;
+
+        thread::sleep(interval);
+        pending = watcher.poll().unwrap_or_else(|err| {
+            // A failed look at the running list shouldn't end the stream
+            eprintln!("{} {:#}", theme.paint("Failed to list applications:", Role::Danger), err);
+            Vec::new()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::OpenOptions;
+
+    fn summary(events: &[Event]) -> Vec<(EventKind, &str, Option<u32>)> {
+        events.iter().map(|event| (event.event, event.name.as_str(), event.pid)).collect()
+    }
+
+    #[test]
+    fn apps_already_running_start_out_quiet() {
+        let _sandbox = backend::sandbox();
+
+        let mut watcher = Watcher::new().unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(
+            summary(&watcher.existing()),
+            [(EventKind::Started, "Finder", Some(1000)), (EventKind::Started, "Terminal", Some(1001))]
+        );
+    }
+
+    #[test]
+    fn reports_each_launch_and_exit_once() {
+        let _sandbox = backend::sandbox();
+        let fake = backend::current();
+        let mut watcher = Watcher::new().unwrap();
+
+        fake.quit("Terminal").unwrap();
+        fake.open("Slack", &OpenOptions::default()).unwrap();
+        let events = watcher.poll().unwrap();
+        // The exit keeps the pid seen at launch, even though Slack has it now
+        assert_eq!(
+            summary(&events),
+            [(EventKind::Exited, "Terminal", Some(1001)), (EventKind::Started, "Slack", Some(1001))]
+        );
+        assert!(watcher.poll().unwrap().is_empty());
+
+        fake.quit("Slack").unwrap();
+        assert_eq!(summary(&watcher.poll().unwrap()), [(EventKind::Exited, "Slack", Some(1001))]);
+    }
+}
@@ -0,0 +1,296 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    backend::OpenOptions,
+    config::Config,
+    duration,
+    get_running_applications,
+    history::KillHistory,
+    kill_specific_application, open_specific_application,
+    protect::ProtectedApps,
+    theme::{self, Role},
+};
+
+/// Name of the project-local workspace file.
+pub const PROJECT_FILE: &str = ".qapp.toml";
+
+/// A set of apps that are opened and quit together.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Workspace {
+    /// Apps to open, in launch order
+    pub apps: Vec<WorkspaceApp>,
+    /// Apps to quit when the workspace comes up
+    pub quit: Vec<String>,
+}
+
+/// An app in a workspace, written as just its name or as a table with options.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawWorkspaceApp")]
+pub struct WorkspaceApp {
+    pub name: String,
+    /// How long to wait after launching before opening the next app
+    pub delay: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawWorkspaceApp {
+    Name(String),
+    Detailed {
+        name: String,
+        delay: Option<String>,
+    },
+}
+
+impl TryFrom<RawWorkspaceApp> for WorkspaceApp {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawWorkspaceApp) -> Result<Self> {
+        Ok(match raw {
+            RawWorkspaceApp::Name(name) => Self { name, delay: None },
+            RawWorkspaceApp::Detailed { name, delay } => Self {
+                delay: delay.as_deref().map(duration::parse).transpose()?,
+                name,
+            },
+        })
+    }
+}
+
+/// What bringing a workspace up or down changed.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub opened: Vec<String>,
+    pub already_running: Vec<String>,
+    pub quit: Vec<String>,
+    pub not_running: Vec<String>,
+    pub protected: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl Summary {
+    pub fn print(&self) {
+        let theme = theme::current();
+
+        let groups = [
+            ("Opened:", &self.opened, Role::Success),
+            ("Already running:", &self.already_running, Role::Warning),
+            ("Quit:", &self.quit, Role::Danger),
+            ("Not running:", &self.not_running, Role::Warning),
+            ("Skipped (protected):", &self.protected, Role::Warning),
+        ];
+
+        for (label, apps, role) in groups {
+            if !apps.is_empty() {
+                println!("{} {}", theme.paint(label, role), theme.paint(&apps.join(", "), Role::Name));
+            }
+        }
+
+        for (app_name, err) in &self.failed {
+            println!("{} {} ({})", theme.paint("Failed:", Role::Danger), theme.paint(app_name, Role::Name), err);
+        }
+
+        if self.opened.is_empty() && self.quit.is_empty() && self.failed.is_empty() {
+            println!("{}", theme.paint("Nothing to do.", Role::Warning));
+        }
+    }
+}
+
+fn is_running(running: &[String], app_name: &str) -> bool {
+    running.iter().any(|app| app.eq_ignore_ascii_case(app_name))
+}
+
+impl Workspace {
+    /// Reads a project file, which has the same fields as a configured workspace.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .context(format!("Failed to read {}", path.display()))?;
+
+        toml::from_str(&contents)
+            .context(format!("Failed to parse {}", path.display()))
+    }
+
+    /// Quits the `quit` apps, then opens every app that isn't running yet.
+    pub fn up(&self, config: &Config) -> Result<Summary> {
+        let mut summary = Summary::default();
+        let running = get_running_applications()?;
+
+        quit_apps(self.quit.iter().map(String::as_str), &running, config, &mut summary)?;
+
+        for app in &self.apps {
+            let app_name = app.name.clone();
+
+            if is_running(&running, &app_name) {
+                summary.already_running.push(app_name);
+                continue;
+            }
+
+            match open_specific_application(&app_name, &OpenOptions::default()) {
+                Ok(()) => {
+                    summary.opened.push(app_name);
+                    if let Some(delay) = app.delay {
+                        thread::sleep(delay);
+                    }
+                },
+                Err(err) => summary.failed.push((app_name, err.to_string())),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Quits every app of the workspace that is running, last launched first.
+    pub fn down(&self, config: &Config) -> Result<Summary> {
+        let mut summary = Summary::default();
+        let running = get_running_applications()?;
+
+        quit_apps(self.apps.iter().rev().map(|app| app.name.as_str()), &running, config, &mut summary)?;
+
+        Ok(summary)
+    }
+}
+
+fn quit_apps<'a>(
+    apps: impl Iterator<Item = &'a str>,
+    running: &[String],
+    config: &Config,
+    summary: &mut Summary,
+) -> Result<()> {
+    let protected = ProtectedApps::load(&config.kill);
+
+    for app_name in apps {
+        let app_name = app_name.to_string();
+
+        if !is_running(running, &app_name) {
+            summary.not_running.push(app_name);
+        } else if protected.contains(&app_name) {
+            summary.protected.push(app_name);
+        } else {
+            match kill_specific_application(&app_name) {
+                Ok(()) => summary.quit.push(app_name),
+                Err(err) => summary.failed.push((app_name, err.to_string())),
+            }
+        }
+    }
+
+    KillHistory::record(&summary.quit).context("Failed to record quit applications")
+}
+
+/// Looks up a workspace defined in the config.
+pub fn named<'a>(config: &'a Config, name: &str) -> Result<&'a Workspace> {
+    if let Some(workspace) = config.workspaces.get(name) {
+        return Ok(workspace);
+    }
+
+    let names: Vec<&str> = config.workspaces.keys().map(String::as_str).collect();
+    bail!(
+        "Unknown workspace: {} (defined: {})",
+        name,
+        if names.is_empty() { "none".to_string() } else { names.join(", ") }
+    )
+}
+
+/// Finds the nearest project file in `start` or one of its parents.
+pub fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend;
+
+    fn workspace(toml: &str) -> Result<Workspace> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.kill.protect_host = false;
+        config
+    }
+
+    #[test]
+    fn reads_apps_as_names_or_tables() {
+        let workspace = workspace(
+            r#"
+            apps = ["Slack", { name = "Docker", delay = "2s" }, { name = "Mail" }]
+            quit = ["Zoom"]
+            "#,
+        )
+        .unwrap();
+
+        let apps: Vec<(&str, Option<Duration>)> =
+            workspace.apps.iter().map(|app| (app.name.as_str(), app.delay)).collect();
+        assert_eq!(apps, [("Slack", None), ("Docker", Some(Duration::from_secs(2))), ("Mail", None)]);
+        assert_eq!(workspace.quit, ["Zoom"]);
+    }
+
+    #[test]
+    fn rejects_bad_delays_and_fields() {
+        assert!(workspace(r#"apps = [{ name = "Docker", delay = "soon" }]"#).is_err());
+        assert!(workspace(r#"apps = [{ delay = "2s" }]"#).is_err());
+        assert!(workspace(r#"open = ["Slack"]"#).is_err());
+    }
+
+    #[test]
+    fn up_quits_then_opens_what_isnt_running() {
+        let _sandbox = backend::sandbox();
+        let workspace = workspace(
+            r#"
+            apps = ["Terminal", "Slack", "Photoshop"]
+            quit = ["Finder", "Mail"]
+            "#,
+        )
+        .unwrap();
+
+        let summary = workspace.up(&config()).unwrap();
+        assert_eq!(summary.protected, ["Finder"]);
+        assert_eq!(summary.not_running, ["Mail"]);
+        assert_eq!(summary.already_running, ["Terminal"]);
+        assert_eq!(summary.opened, ["Slack"]);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(get_running_applications().unwrap(), ["Finder", "Terminal", "Slack"]);
+    }
+
+    #[test]
+    fn down_quits_in_reverse_launch_order() {
+        let _sandbox = backend::sandbox();
+        let workspace = workspace(r#"apps = ["Terminal", "Mail", "Finder"]"#).unwrap();
+        backend::current().open("Mail", &OpenOptions::default()).unwrap();
+
+        let summary = workspace.down(&config()).unwrap();
+        assert_eq!(summary.protected, ["Finder"]);
+        assert_eq!(summary.quit, ["Mail", "Terminal"]);
+        assert_eq!(KillHistory::pop().unwrap().unwrap().apps, ["Mail", "Terminal"]);
+    }
+
+    #[test]
+    fn finds_the_nearest_project_file_upwards() {
+        let root = std::env::temp_dir().join(format!("qapp-project-{}", std::process::id()));
+        let nested = root.join("app").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(PROJECT_FILE), r#"apps = ["Docker"]"#).unwrap();
+
+        assert_eq!(find_project_file(&nested), Some(root.join(PROJECT_FILE)));
+        assert_eq!(Workspace::load(&root.join(PROJECT_FILE)).unwrap().apps[0].name, "Docker");
+
+        // A directory named like the file doesn't count
+        fs::create_dir(nested.join(PROJECT_FILE)).unwrap();
+        fs::write(root.join("app").join(PROJECT_FILE), "quit = [").unwrap();
+        assert_eq!(find_project_file(&nested), Some(root.join("app").join(PROJECT_FILE)));
+        assert!(Workspace::load(&root.join("app").join(PROJECT_FILE)).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}